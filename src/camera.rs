@@ -1,11 +1,13 @@
 use bevy::{prelude::*, render::camera::ScalingMode};
 
-use crate::player::Player;
+use crate::{input::{Action, Actions}, level::scroll_stop::LevelBounds, player::Player};
 
 pub const WINDOW_SIZE: Vec2 = Vec2::new(1024., 720.);
 pub const WINDOW_BOTTOM_LEFT: Vec2 = Vec2::new(WINDOW_SIZE.x / -2., WINDOW_SIZE.y / -2.);
 pub const WINDOW_TOP_RIGHT: Vec2 = Vec2::new(WINDOW_SIZE.x / 2., WINDOW_SIZE.y / 2.);
 
+/// How far past the level bounds the editor camera may pan
+const EDITOR_PAN_MARGIN: f32 = 100.;
 
 // SYSTEMS
 fn setup_camera(mut cmd: Commands) {
@@ -25,35 +27,28 @@ fn setup_camera(mut cmd: Commands) {
 
 fn move_camera_game(
     mut camera_query: Query<&mut Transform, With<Camera>>,
-    player_query: Query<&Transform, (With<Player>, Without<Camera>)>
+    player_query: Query<&Transform, (With<Player>, Without<Camera>)>,
+    bounds_query: Query<&LevelBounds>,
 ) {
     let Ok(mut camera) = camera_query.get_single_mut() else { return };
     let Ok(player) = player_query.get_single() else { return };
+    let bounds = bounds_query.get_single().copied().unwrap_or_default();
 
-    camera.translation = Vec3::new(f32::max(player.translation.x, 0.), f32::max(player.translation.y, 0.), camera.translation.z);
+    camera.translation = bounds.clamp_camera(player.translation.truncate(), 0.).extend(camera.translation.z);
 }
 
 fn move_camera_editor(
     mut camera_query: Query<&mut Transform, With<Camera>>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Actions,
+    bounds_query: Query<&LevelBounds>,
 ) {
-    let left = keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]);
-    let right = keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]);
-
-    let up = keyboard_input.any_pressed([KeyCode::W, KeyCode::Up]);
-    let down = keyboard_input.any_pressed([KeyCode::S, KeyCode::Down]);
-
-    let motion = Vec2::new((right as i8 - left as i8) as f32, (up as i8 - down as i8) as f32) * 2.;
+    let motion = Vec2::new(actions.axis(Action::CameraLeft, Action::CameraRight), actions.axis(Action::CameraDown, Action::CameraUp)) * 2.;
     let Ok(mut camera_transform) = camera_query.get_single_mut() else { return };
     camera_transform.translation += motion.extend(0.);
 
-    if camera_transform.translation.x <= 0. {
-        camera_transform.translation.x = 0.;
-    }
-
-    if camera_transform.translation.y <= 0. {
-        camera_transform.translation.y = 0.;
-    }
+    let bounds = bounds_query.get_single().copied().unwrap_or_default();
+    let position = bounds.clamp_camera(camera_transform.translation.truncate(), EDITOR_PAN_MARGIN);
+    camera_transform.translation = position.extend(camera_transform.translation.z);
 }
 
 // PLUGIN
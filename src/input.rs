@@ -0,0 +1,262 @@
+//! Named actions and the keys and gamepad buttons bound to them
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, ecs::system::SystemParam, reflect::{DynamicEnum, DynamicVariant, Enum}, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::save::UserSettings;
+
+// ACTIONS
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, EnumIter)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Jump,
+    NextGroup,
+    PrevGroup,
+    ScaleUp,
+    ScaleDown,
+    FineScale,
+    CoarseScale,
+    SetScale,
+    Undo,
+    Redo,
+    /// Held with undo or redo, so they aren't pressed by accident
+    HistoryModifier,
+    Interact,
+    Pause,
+    /// Accepts or backs out of a prompt
+    Confirm,
+    Cancel,
+    /// Pans the level editor's camera
+    CameraLeft,
+    CameraRight,
+    CameraUp,
+    CameraDown,
+}
+
+impl Action {
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::Jump => "Jump",
+            Action::NextGroup => "Next Group",
+            Action::PrevGroup => "Previous Group",
+            Action::ScaleUp => "Scale Up",
+            Action::ScaleDown => "Scale Down",
+            Action::FineScale => "Fine Scaling",
+            Action::CoarseScale => "Coarse Scaling",
+            Action::SetScale => "Set Exact Scale",
+            Action::Undo => "Undo",
+            Action::Redo => "Redo",
+            Action::HistoryModifier => "Undo/Redo Modifier",
+            Action::Interact => "Interact",
+            Action::Pause => "Pause",
+            Action::Confirm => "Confirm",
+            Action::Cancel => "Cancel",
+            Action::CameraLeft => "Editor Camera Left",
+            Action::CameraRight => "Editor Camera Right",
+            Action::CameraUp => "Editor Camera Up",
+            Action::CameraDown => "Editor Camera Down",
+        }
+    }
+
+    fn default_keys(self) -> Vec<KeyCode> {
+        match self {
+            Action::MoveLeft => vec![KeyCode::A, KeyCode::Left],
+            Action::MoveRight => vec![KeyCode::D, KeyCode::Right],
+            Action::Jump => vec![KeyCode::Space],
+            Action::NextGroup => vec![KeyCode::W, KeyCode::Up],
+            Action::PrevGroup => vec![KeyCode::S, KeyCode::Down],
+            Action::ScaleUp => vec![KeyCode::E],
+            Action::ScaleDown => vec![KeyCode::Q],
+            Action::FineScale => vec![KeyCode::AltLeft, KeyCode::AltRight],
+            Action::CoarseScale => vec![KeyCode::ShiftLeft, KeyCode::ShiftRight],
+            Action::SetScale => vec![KeyCode::Return],
+            Action::Undo => vec![KeyCode::Z],
+            Action::Redo => vec![KeyCode::Y],
+            Action::HistoryModifier => vec![KeyCode::ControlLeft, KeyCode::ControlRight],
+            Action::Interact => vec![KeyCode::F],
+            Action::Pause => vec![KeyCode::Escape],
+            Action::Confirm => vec![KeyCode::Return, KeyCode::NumpadEnter],
+            Action::Cancel => vec![KeyCode::Escape],
+            Action::CameraLeft => vec![KeyCode::A, KeyCode::Left],
+            Action::CameraRight => vec![KeyCode::D, KeyCode::Right],
+            Action::CameraUp => vec![KeyCode::W, KeyCode::Up],
+            Action::CameraDown => vec![KeyCode::S, KeyCode::Down],
+        }
+    }
+
+    /// Gamepad buttons are fixed, and analog scaling is read from the triggers and right stick instead
+    fn gamepad_buttons(self) -> &'static [GamepadButtonType] {
+        match self {
+            Action::MoveLeft => &[GamepadButtonType::DPadLeft],
+            Action::MoveRight => &[GamepadButtonType::DPadRight],
+            Action::Jump => &[GamepadButtonType::South],
+            Action::NextGroup => &[GamepadButtonType::RightTrigger],
+            Action::PrevGroup => &[GamepadButtonType::LeftTrigger],
+            Action::ScaleUp | Action::ScaleDown | Action::FineScale | Action::CoarseScale | Action::SetScale => &[],
+            Action::Undo | Action::Redo | Action::HistoryModifier => &[],
+            Action::Interact => &[GamepadButtonType::West],
+            Action::Pause => &[GamepadButtonType::Start],
+            Action::Confirm => &[GamepadButtonType::South],
+            Action::Cancel => &[GamepadButtonType::East],
+            Action::CameraLeft => &[GamepadButtonType::DPadLeft],
+            Action::CameraRight => &[GamepadButtonType::DPadRight],
+            Action::CameraUp => &[GamepadButtonType::DPadUp],
+            Action::CameraDown => &[GamepadButtonType::DPadDown],
+        }
+    }
+}
+
+/// Rescales `value` so the deadzone reads as 0 and the rest of the range still reaches 1
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        0.
+    } else {
+        value.signum() * (value.abs() - deadzone) / (1. - deadzone)
+    }
+}
+
+// RESOURCES
+/// The keys bound to each action, saved by name so the file stays readable
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "BTreeMap<Action, Vec<String>>", into = "BTreeMap<Action, Vec<String>>")]
+pub struct InputBindings(HashMap<Action, Vec<KeyCode>>);
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self(Action::iter().map(|action| (action, action.default_keys())).collect())
+    }
+}
+
+impl InputBindings {
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn bind(&mut self, action: Action, keys: Vec<KeyCode>) {
+        self.0.insert(action, keys);
+    }
+
+    /// The bound keys, as shown in the settings menu
+    pub fn describe(&self, action: Action) -> String {
+        let keys = self.keys(action);
+        if keys.is_empty() {
+            return String::from("Unbound");
+        }
+
+        keys.iter().map(|key| key.variant_name()).collect::<Vec<_>>().join(" / ")
+    }
+}
+
+/// Falls back to the default keys for actions missing from the file
+impl From<BTreeMap<Action, Vec<String>>> for InputBindings {
+    fn from(saved: BTreeMap<Action, Vec<String>>) -> Self {
+        let mut bindings = Self::default();
+        for (action, names) in saved {
+            let keys = names
+                .iter()
+                .filter_map(|name| {
+                    let key = KeyCode::from_reflect(&DynamicEnum::new(name.as_str(), DynamicVariant::Unit));
+                    if key.is_none() {
+                        warn!("Ignoring unknown key {name:?} bound to {action:?}");
+                    }
+                    key
+                })
+                .collect();
+            bindings.bind(action, keys);
+        }
+        bindings
+    }
+}
+
+impl From<InputBindings> for BTreeMap<Action, Vec<String>> {
+    fn from(bindings: InputBindings) -> Self {
+        bindings.0
+            .into_iter()
+            .map(|(action, keys)| (action, keys.iter().map(|key| key.variant_name().to_owned()).collect()))
+            .collect()
+    }
+}
+
+// SYSTEM PARAMS
+/// Reads actions through the current bindings from the keyboard and every gamepad, so systems never look at raw input
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    bindings: Res<'w, InputBindings>,
+    keys: Res<'w, Input<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    buttons: Res<'w, Input<GamepadButton>>,
+    button_axes: Res<'w, Axis<GamepadButton>>,
+    axes: Res<'w, Axis<GamepadAxis>>,
+    settings: Res<'w, UserSettings>,
+}
+
+impl Actions<'_> {
+    fn gamepad_buttons(&self, action: Action) -> impl Iterator<Item = GamepadButton> + '_ {
+        self.gamepads
+            .iter()
+            .flat_map(move |gamepad| action.gamepad_buttons().iter().map(move |&button| GamepadButton::new(gamepad, button)))
+    }
+
+    /// The strongest deflection of a stick axis across all gamepads
+    fn stick(&self, axis: GamepadAxisType) -> f32 {
+        self.gamepads
+            .iter()
+            .filter_map(|gamepad| self.axes.get(GamepadAxis::new(gamepad, axis)))
+            .map(|value| apply_deadzone(value, self.settings.deadzone))
+            .fold(0., |strongest, value| if value.abs() > strongest.abs() { value } else { strongest })
+    }
+
+    /// The furthest any gamepad's analog trigger is held
+    fn trigger(&self, button: GamepadButtonType) -> f32 {
+        self.gamepads
+            .iter()
+            .filter_map(|gamepad| self.button_axes.get(GamepadButton::new(gamepad, button)))
+            .map(|value| apply_deadzone(value, self.settings.deadzone))
+            .fold(0., f32::max)
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.keys.any_pressed(self.bindings.keys(action).iter().copied())
+            || self.buttons.any_pressed(self.gamepad_buttons(action))
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.keys.any_just_pressed(self.bindings.keys(action).iter().copied())
+            || self.buttons.any_just_pressed(self.gamepad_buttons(action))
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.keys.any_just_released(self.bindings.keys(action).iter().copied())
+            || self.buttons.any_just_released(self.gamepad_buttons(action))
+    }
+
+    /// -1, 0 or 1 depending on which of the two actions are held
+    pub fn axis(&self, negative: Action, positive: Action) -> f32 {
+        (self.pressed(positive) as i8 - self.pressed(negative) as i8) as f32
+    }
+
+    /// Horizontal movement from -1 to 1, from the move actions or the left stick
+    pub fn movement(&self) -> f32 {
+        (self.axis(Action::MoveLeft, Action::MoveRight) + self.stick(GamepadAxisType::LeftStickX)).clamp(-1., 1.)
+    }
+
+    /// How fast to scale the selected group from -1 to 1, from the triggers or the right stick
+    pub fn analog_scale(&self) -> f32 {
+        let triggers = self.trigger(GamepadButtonType::RightTrigger2) - self.trigger(GamepadButtonType::LeftTrigger2);
+        (triggers + self.stick(GamepadAxisType::RightStickY)).clamp(-1., 1.)
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputBindings>();
+    }
+}
@@ -1,25 +1,105 @@
 // TODO: level system w/ YOLECK
+use std::collections::BTreeMap;
+
 use bevy::prelude::*;
 use bevy_yoleck::{prelude::*, YoleckManaged};
+use serde::{Deserialize, Serialize};
 
 use crate::{GameplaySet, GameState};
 
 // SUBMODULES
+pub mod checkpoint;
+pub mod completion;
+pub mod hazard;
+pub mod lock;
 pub mod platform;
 pub mod resizable;
+pub mod scale_prompt;
+pub mod scroll_stop;
+pub mod settings;
+pub mod spawn_point;
+pub mod switch;
 pub mod text;
-// TODO: pub mod scroll_stop;
 pub mod level_portal;
 
 // RESOURCES
 #[derive(Resource)]
-pub struct CurrentLevel(Option<usize>);
+pub struct CurrentLevel(pub Option<usize>);
+
+/// The list of levels, loaded once at startup
+#[derive(Resource)]
+pub struct LevelIndex(pub Handle<YoleckLevelIndex>);
+
+/// The player's best results on a level
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+#[serde(default)]
+pub struct LevelRecord {
+    pub completed: bool,
+    /// In seconds
+    pub best_time: Option<f32>,
+    pub fewest_deaths: Option<u32>,
+    pub fewest_scale_changes: Option<u32>,
+}
+
+/// How far the player has got through the level index
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct LevelProgress {
+    /// The highest level number the player may start from
+    pub unlocked: usize,
+    pub levels: BTreeMap<usize, LevelRecord>,
+}
+
+impl Default for LevelProgress {
+    fn default() -> Self {
+        Self {
+            unlocked: 1,
+            levels: BTreeMap::new(),
+        }
+    }
+}
+
+impl LevelProgress {
+    /// Records a finished run of `level`, unlocking the level it led to
+    pub fn complete(&mut self, level: usize, next: usize, run: &LevelRun) {
+        let record = self.levels.entry(level).or_default();
+        record.completed = true;
+        improve(&mut record.best_time, run.time);
+        improve(&mut record.fewest_deaths, run.deaths);
+        improve(&mut record.fewest_scale_changes, run.scale_changes);
+
+        self.unlocked = self.unlocked.max(next);
+    }
+}
+
+/// Replaces `best` with `value` if it is lower
+fn improve<T: PartialOrd + Copy>(best: &mut Option<T>, value: T) {
+    if best.filter(|best| *best <= value).is_none() {
+        *best = Some(value);
+    }
+}
+
+/// Statistics for the current attempt at a level
+#[derive(Resource, Clone, Default)]
+pub struct LevelRun {
+    /// In seconds
+    pub time: f32,
+    pub deaths: u32,
+    pub scale_changes: u32,
+}
+
+// EVENTS
+/// Sent once the entities of a newly loaded level have been populated, whether it is being played or edited
+#[derive(Event)]
+pub struct LevelLoaded;
 
 // SYSTEMS
+fn load_level_index(mut cmd: Commands, asset_server: Res<AssetServer>) {
+    cmd.insert_resource(LevelIndex(asset_server.load("levels/index.yoli")));
+}
+
 pub fn load_level(
-    mut cmd: Commands,
-    level_entities_query: Query<Entity, With<YoleckManaged>>,
-    mut level_index_handle: Local<Option<Handle<YoleckLevelIndex>>>,
+    level_index: Res<LevelIndex>,
     level_index_assets: Res<Assets<YoleckLevelIndex>>, 
     asset_server: Res<AssetServer>,
     mut yoleck_loading_cmd: ResMut<YoleckLoadingCommand>,
@@ -27,16 +107,9 @@ pub fn load_level(
     mut state: ResMut<NextState<GameState>>,
 ) {
     if let Some(level) = current_level.0 {
-        let level_index_handle: Handle<YoleckLevelIndex> = level_index_handle
-            .get_or_insert_with(|| asset_server.load("levels/index.yoli"))
-            .clone();
-        let Some(level_index) = level_index_assets.get(&level_index_handle) else { return };
+        let Some(level_index) = level_index_assets.get(&level_index.0) else { return };
         bevy::log::info!("Loading level {level}");
 
-        for entity in level_entities_query.iter() {
-            cmd.entity(entity).despawn_recursive();
-        }
-
         let level_handle: Handle<YoleckRawLevel> = asset_server.load(
             format!("levels/{}", level_index[level - 1].filename)
         );
@@ -45,6 +118,41 @@ pub fn load_level(
     }
 }
 
+/// Every attempt at a level starts without a checkpoint, keys or statistics
+fn start_attempt(
+    mut active_checkpoint: ResMut<checkpoint::ActiveCheckpoint>,
+    mut held_keys: ResMut<lock::HeldKeys>,
+    mut run: ResMut<LevelRun>,
+) {
+    active_checkpoint.0 = None;
+    *held_keys = lock::HeldKeys::default();
+    *run = LevelRun::default();
+}
+
+/// Clears the level away before loading another, or when returning to the menu, where it would otherwise still show
+fn unload_level(mut cmd: Commands, level_entities_query: Query<Entity, With<YoleckManaged>>) {
+    for entity in level_entities_query.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+/// Yoleck clears its loading command once the level's entities exist
+fn detect_level_loaded(
+    loading_cmd: Res<YoleckLoadingCommand>,
+    mut was_loading: Local<bool>,
+    mut evw: EventWriter<LevelLoaded>,
+) {
+    let loading = !matches!(*loading_cmd, YoleckLoadingCommand::NoCommand);
+    if *was_loading && !loading {
+        evw.send(LevelLoaded);
+    }
+    *was_loading = loading;
+}
+
+fn tick_level_run(time: Res<Time>, mut run: ResMut<LevelRun>) {
+    run.time += time.delta_seconds();
+}
+
 // TODO: MENU
 fn unset_level(mut lvl: ResMut<CurrentLevel>) {
     lvl.0 = None;
@@ -56,10 +164,18 @@ pub struct Plugin;
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app
-            .insert_resource(CurrentLevel(Some(1)))
-            .add_plugins((level_portal::Plugin, platform::Plugin, resizable::Plugin, text::Plugin))
+            .insert_resource(CurrentLevel(None))
+            .init_resource::<LevelProgress>()
+            .init_resource::<LevelRun>()
+            .add_event::<LevelLoaded>()
+            .add_systems(Startup, load_level_index)
+            .add_systems(PostUpdate, detect_level_loaded)
+            .add_plugins((checkpoint::Plugin, completion::Plugin, hazard::Plugin, level_portal::Plugin, lock::Plugin, platform::Plugin, resizable::Plugin, scale_prompt::Plugin, scroll_stop::Plugin, settings::Plugin, spawn_point::Plugin, switch::Plugin, text::Plugin))
             .add_systems(OnExit(GameState::InGame), unset_level.run_if(not(in_state(GameState::Loading))))
             .add_systems(OnEnter(GameState::LevelEditor), unset_level)
-            .add_systems(Update, load_level.run_if(in_state(GameState::Loading)));
+            .add_systems(OnEnter(GameState::Menu), unload_level)
+            .add_systems(OnEnter(GameState::Loading), (unload_level, start_attempt))
+            .add_systems(Update, load_level.run_if(in_state(GameState::Loading)))
+            .add_systems(Update, tick_level_run.in_set(GameplaySet::Update));
     }
 }
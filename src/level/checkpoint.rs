@@ -0,0 +1,147 @@
+use bevy::{prelude::*, utils::HashMap};
+use bevy_xpbd_2d::{prelude::*, math::*};
+use bevy_yoleck::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameplaySet, player::{Player, respawn::Respawn}};
+
+use super::resizable::{Scale, ScaleGroup, ScaleGroupState};
+
+const INACTIVE_COLOR: Color = Color::GRAY;
+const ACTIVE_COLOR: Color = Color::GOLD;
+
+// COMPONENTS
+#[derive(Component)]
+pub struct Checkpoint;
+
+/// The player's position and every group's scale when a checkpoint was reached
+pub struct CheckpointSnapshot {
+    checkpoint: Entity,
+    pub position: Vec2,
+    scales: HashMap<ScaleGroup, Scalar>,
+}
+
+// RESOURCES
+/// The checkpoint the player will respawn at, cleared whenever a level is loaded
+#[derive(Resource, Default)]
+pub struct ActiveCheckpoint(pub Option<CheckpointSnapshot>);
+
+// BUNDLE
+#[derive(Bundle)]
+pub struct CheckpointBundle {
+    sprite: SpriteBundle,
+    trigger: ShapeCaster,
+    marker: Checkpoint,
+}
+
+impl CheckpointBundle {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(15., 50.)),
+                    color: INACTIVE_COLOR,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(1.)),
+                ..Default::default()
+            },
+            trigger: ShapeCaster::new(Collider::cuboid(15., 50.), Vector::new(position.x, position.y), 0.0, Vector::ONE)
+                .with_max_time_of_impact(10.)
+                .with_max_hits(8),
+            marker: Checkpoint,
+        }
+    }
+}
+
+// SYSTEMS
+fn handle_checkpoint_interactions(
+    checkpoint_query: Query<(Entity, &ShapeHits), With<Checkpoint>>,
+    player_query: Query<&Transform, With<Player>>,
+    scales: Query<(&ScaleGroup, &Scale)>,
+    mut active: ResMut<ActiveCheckpoint>,
+) {
+    for (checkpoint, hits) in checkpoint_query.iter() {
+        let Some(player) = hits.iter().find_map(|data| player_query.get(data.entity).ok()) else { continue };
+        if active.0.as_ref().is_some_and(|snapshot| snapshot.checkpoint == checkpoint) {
+            continue;
+        }
+
+        active.0 = Some(CheckpointSnapshot {
+            checkpoint,
+            position: player.translation.truncate(),
+            scales: scales.iter().map(|(group, scale)| (group.clone(), scale.get())).collect(),
+        });
+    }
+}
+
+pub fn restore_checkpoint_scales(
+    mut evr: EventReader<Respawn>,
+    active: Res<ActiveCheckpoint>,
+    mut groups: Query<ScaleGroupState>,
+) {
+    if evr.read().last().is_none() {
+        return;
+    }
+    let Some(snapshot) = active.0.as_ref() else { return };
+
+    for mut group in groups.iter_mut() {
+        if let Some(&scale) = snapshot.scales.get(group.group) {
+            group.set(scale);
+        }
+    }
+}
+
+fn update_checkpoint_colors(
+    mut checkpoint_query: Query<(Entity, &mut Sprite), With<Checkpoint>>,
+    active: Res<ActiveCheckpoint>,
+) {
+    let active = active.0.as_ref().map(|snapshot| snapshot.checkpoint);
+    for (checkpoint, mut sprite) in checkpoint_query.iter_mut() {
+        let color = if Some(checkpoint) == active { ACTIVE_COLOR } else { INACTIVE_COLOR };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent, Default)]
+pub struct YoleckCheckpoint {
+    x: Scalar,
+    y: Scalar,
+}
+
+fn populate_checkpoint(mut pop: YoleckPopulate<&YoleckCheckpoint>) {
+    pop.populate(|_ctx, mut cmd, checkpoint| {
+        cmd.insert(CheckpointBundle::new(Vec2::new(checkpoint.x, checkpoint.y)));
+    })
+}
+
+fn edit_checkpoint(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut YoleckCheckpoint>) {
+    if let Ok(mut checkpoint) = edit.get_single_mut() {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut checkpoint.x).speed(1.).fixed_decimals(0).prefix("X: "));
+            ui.add(egui::DragValue::new(&mut checkpoint.y).speed(1.).fixed_decimals(0).prefix("Y: "));
+        });
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ActiveCheckpoint>()
+            .add_systems(Update, (
+                handle_checkpoint_interactions.in_set(GameplaySet::Update),
+                restore_checkpoint_scales.in_set(GameplaySet::Movement),
+                update_checkpoint_colors,
+            ));
+
+        app.add_yoleck_entity_type(YoleckEntityType::new("Checkpoint").with::<YoleckCheckpoint>());
+        app.add_yoleck_edit_system(edit_checkpoint);
+        app.yoleck_populate_schedule_mut().add_systems(populate_checkpoint);
+    }
+}
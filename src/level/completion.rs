@@ -0,0 +1,98 @@
+//! The level complete screen shown after reaching a portal
+use bevy::prelude::*;
+
+use crate::{GameState, PauseState, menu, pause::OVERLAY_BG};
+
+use super::{CurrentLevel, LevelRecord, LevelRun};
+
+// RESOURCES
+/// A finished run, waiting for the player to continue or retry
+#[derive(Resource)]
+pub struct LevelResult {
+    pub level: usize,
+    /// The level the portal leads to
+    pub next: usize,
+    pub run: LevelRun,
+    /// The saved record from before this run
+    pub previous: Option<LevelRecord>,
+}
+
+// COMPONENTS
+#[derive(Component, Clone, Copy)]
+pub enum CompletionButton {
+    Continue,
+    Retry,
+}
+
+#[derive(Component)]
+pub struct CompletionRoot;
+
+// HELPERS
+/// Describes a statistic where lower is better, compared to the previous best
+fn stat_line<T: PartialOrd + Copy>(label: &str, value: T, best: Option<T>, format: impl Fn(T) -> String) -> String {
+    match best {
+        Some(best) if best <= value => format!("{label}: {} (best {})", format(value), format(best)),
+        Some(_) => format!("{label}: {} - new best!", format(value)),
+        None => format!("{label}: {}", format(value)),
+    }
+}
+
+// SYSTEMS
+fn setup_screen(mut cmd: Commands, result: Res<LevelResult>) {
+    let previous = result.previous.clone().unwrap_or_default();
+    let lines = [
+        stat_line("Time", result.run.time, previous.best_time, |time| format!("{time:.2}s")),
+        stat_line("Deaths", result.run.deaths, previous.fewest_deaths, |deaths| deaths.to_string()),
+        stat_line("Scale changes", result.run.scale_changes, previous.fewest_scale_changes, |changes| changes.to_string()),
+    ];
+
+    let root = menu::spawn_screen(&mut cmd, &format!("Level {} Complete", result.level), CompletionRoot, |parent| {
+        for line in lines {
+            parent.spawn(TextBundle::from_section(line, TextStyle {
+                font_size: 24.,
+                color: Color::ANTIQUE_WHITE,
+                ..Default::default()
+            }));
+        }
+        menu::spawn_button(parent, "Continue", CompletionButton::Continue, false);
+        menu::spawn_button(parent, "Retry", CompletionButton::Retry, false);
+    });
+    cmd.entity(root).insert((BackgroundColor(OVERLAY_BG), ZIndex::Global(1)));
+}
+
+fn despawn_screen(mut cmd: Commands, q: Query<Entity, With<CompletionRoot>>) {
+    for entity in q.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn handle_completion_buttons(
+    buttons: Query<(&Interaction, &CompletionButton), Changed<Interaction>>,
+    result: Res<LevelResult>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        current_level.0 = Some(match *button {
+            CompletionButton::Continue => result.next,
+            CompletionButton::Retry => result.level,
+        });
+        state.set(GameState::Loading);
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(PauseState::LevelComplete), setup_screen)
+            .add_systems(OnExit(PauseState::LevelComplete), despawn_screen)
+            .add_systems(Update, handle_completion_buttons.run_if(in_state(PauseState::LevelComplete)));
+    }
+}
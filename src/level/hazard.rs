@@ -0,0 +1,181 @@
+//! Spikes and other hazards which kill the player on contact, optionally scaling with a group
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+use bevy_yoleck::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{EditorSet, GameplaySet, player::{Player, respawn::Respawn}};
+
+use super::{LevelRun, platform::PlatformBundle, resizable::{ScalableBundle, ScaleGroup, ScaleGroupInfo, YoleckScalable}};
+
+const HAZARD_COLOR: Color = Color::rgb(0.3, 0.05, 0.08);
+const SPIKE_COLOR: Color = Color::rgb(0.95, 0.25, 0.2);
+const KILL_REGION_COLOR: Color = Color::FUCHSIA;
+/// The rough width of each spike drawn along a hazard's top edge
+const SPIKE_WIDTH: f32 = 12.;
+
+// COMPONENTS
+/// Hazards are sensors, so they never block scaling or squish the player; touching one kills instead
+#[derive(Component)]
+pub struct Hazard {
+    /// The size before any scaling
+    size: Vec2,
+}
+
+impl Hazard {
+    /// The centre and size of the region that kills the player
+    fn region(&self, transform: &Transform) -> (Vec2, Vec2) {
+        (transform.translation.truncate(), self.size * transform.scale.truncate())
+    }
+}
+
+// RESOURCES
+/// Set when the player respawns, until the next physics step has updated who is touching each hazard
+#[derive(Resource, Default)]
+struct StaleContacts(bool);
+
+// BUNDLE
+/// Turns a platform or scalable object into a hazard
+#[derive(Bundle)]
+pub struct HazardBundle {
+    sprite: Sprite,
+    sensor: Sensor,
+    colliding: CollidingEntities,
+    hazard: Hazard,
+}
+
+impl HazardBundle {
+    pub fn new(size: Vec2) -> Self {
+        Self {
+            sprite: Sprite {
+                custom_size: Some(size),
+                color: HAZARD_COLOR,
+                ..Default::default()
+            },
+            sensor: Sensor,
+            colliding: CollidingEntities::default(),
+            hazard: Hazard { size },
+        }
+    }
+}
+
+// SYSTEMS
+fn check_hazards(
+    hazards: Query<&CollidingEntities, With<Hazard>>,
+    player_query: Query<(), With<Player>>,
+    mut run: ResMut<LevelRun>,
+    mut stale: ResMut<StaleContacts>,
+    mut respawn_evr: EventReader<Respawn>,
+    mut evw: EventWriter<Respawn>,
+) {
+    // Contacts from before a respawn would kill the player again at the respawn point
+    if respawn_evr.read().count() > 0 {
+        stale.0 = true;
+    }
+    if stale.0 {
+        return;
+    }
+
+    let touched = hazards.iter().any(|colliding| colliding.0.iter().any(|&entity| player_query.contains(entity)));
+    if touched {
+        run.deaths += 1;
+        stale.0 = true;
+        evw.send(Respawn);
+    }
+}
+
+fn refresh_contacts(mut stale: ResMut<StaleContacts>) {
+    stale.0 = false;
+}
+
+/// Draws spikes along the top of every hazard, outlined in the colour of its group if it has one
+fn draw_spikes(
+    mut gizmos: Gizmos,
+    hazards: Query<(&Hazard, &Transform, Option<&ScaleGroup>)>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+) {
+    for (hazard, transform, group) in hazards.iter() {
+        let (centre, size) = hazard.region(transform);
+        let top_left = centre + Vec2::new(-size.x, size.y) / 2.;
+        let step = size.x / (size.x / SPIKE_WIDTH).round().max(1.);
+        let depth = (step / 2.).min(size.y);
+
+        let spikes = (0..=(size.x / step * 2.).round() as usize)
+            .map(|i| top_left + Vec2::new(i as f32 * step / 2., if i % 2 == 1 { -depth } else { 0. }));
+        gizmos.linestrip_2d(spikes, SPIKE_COLOR);
+
+        if let Some((_, info)) = group.and_then(|group| groups.iter().find(|(other, _)| **other == *group)) {
+            gizmos.rect_2d(centre, 0., size, info.color());
+        }
+    }
+}
+
+fn draw_kill_regions(mut gizmos: Gizmos, hazards: Query<(&Hazard, &Transform)>) {
+    for (hazard, transform) in hazards.iter() {
+        let (centre, size) = hazard.region(transform);
+        let half = size / 2.;
+
+        gizmos.rect_2d(centre, 0., size, KILL_REGION_COLOR);
+        gizmos.line_2d(centre - half, centre + half, KILL_REGION_COLOR);
+        gizmos.line_2d(centre + Vec2::new(-half.x, half.y), centre + Vec2::new(half.x, -half.y), KILL_REGION_COLOR);
+    }
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent, Default)]
+pub struct YoleckHazard {
+    #[serde(flatten)]
+    shape: YoleckScalable,
+    /// Static hazards ignore the scaling settings of their shape
+    #[serde(default)]
+    scales: bool,
+}
+
+fn populate_hazard(mut pop: YoleckPopulate<&YoleckHazard>) {
+    pop.populate(|_ctx, mut cmd, hazard| {
+        let size = hazard.shape.size();
+        if hazard.scales {
+            cmd.insert(hazard.shape.bundle());
+        } else {
+            // The hazard may have just stopped scaling in the editor
+            cmd.remove::<ScalableBundle>();
+            cmd.insert(PlatformBundle::new(hazard.shape.position(), size));
+        }
+        cmd.insert(HazardBundle::new(size));
+    })
+}
+
+fn edit_hazard(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut YoleckHazard>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+) {
+    if let Ok(mut hazard) = edit.get_single_mut() {
+        ui.checkbox(&mut hazard.scales, "Scales with a group");
+        if hazard.scales {
+            hazard.shape.edit_scaling(&mut ui, &groups);
+        }
+        hazard.shape.edit_shape(&mut ui);
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<StaleContacts>()
+            .add_systems(PhysicsSchedule, refresh_contacts.after(PhysicsStepSet::SpatialQuery));
+
+        app.add_systems(Update, (
+            check_hazards.in_set(GameplaySet::Update),
+            draw_spikes,
+            draw_kill_regions.in_set(EditorSet),
+        ));
+
+        app.add_yoleck_entity_type(YoleckEntityType::new("Hazard").with::<YoleckHazard>());
+        app.add_yoleck_edit_system(edit_hazard);
+        app.yoleck_populate_schedule_mut().add_systems(populate_hazard);
+    }
+}
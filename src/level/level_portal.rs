@@ -1,11 +1,11 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, ecs::system::SystemParam};
 use bevy_xpbd_2d::{prelude::*, math::*};
 use bevy_yoleck::prelude::*;
 use serde::{Serialize, Deserialize};
 
-use crate::{player::Player, GameplaySet, GameState};
+use crate::{player::Player, GameplaySet, GameState, PauseState};
 
-use super::CurrentLevel;
+use super::{CurrentLevel, LevelProgress, LevelRun, completion::LevelResult};
 
 // COMPONENTS
 #[derive(Component)]
@@ -41,18 +41,50 @@ impl LevelPortalBundle {
     }
 }
 
+// SYSTEM PARAMS
+/// Everything a portal touches when the player leaves a level through it
+#[derive(SystemParam)]
+pub struct LevelExit<'w, 's> {
+    cmd: Commands<'w, 's>,
+    state: ResMut<'w, NextState<GameState>>,
+    pause: ResMut<'w, NextState<PauseState>>,
+    current_level: ResMut<'w, CurrentLevel>,
+    progress: ResMut<'w, LevelProgress>,
+    run: Res<'w, LevelRun>,
+}
+
+impl LevelExit<'_, '_> {
+    /// Records the run and shows the results, or goes straight to `next` if there is nothing to record
+    fn leave(&mut self, next: usize) {
+        let Some(level) = self.current_level.0 else {
+            // Playtesting from the editor, so there's nothing to record
+            self.current_level.0 = Some(next);
+            self.state.set(GameState::Loading);
+            return;
+        };
+
+        self.cmd.insert_resource(LevelResult {
+            level,
+            next,
+            run: self.run.clone(),
+            previous: self.progress.levels.get(&level).cloned(),
+        });
+        self.progress.complete(level, next, &self.run);
+        self.pause.set(PauseState::LevelComplete);
+    }
+}
+
 // SYSTEMS
 pub fn handle_portal_interactions(
     transition_query: Query<(&ShapeHits, &LevelTransition), With<LevelTransition>>,
     player_query: Query<Has<Player>>,
-    mut state: ResMut<NextState<GameState>>,
-    mut current_level: ResMut<CurrentLevel>,
+    mut exit: LevelExit,
 ) {
-    for (hits, transition) in transition_query.iter() {
-        if hits.iter().any(|data| player_query.get(data.entity).unwrap_or(false)) {
-            current_level.0 = Some(transition.0);
-            state.set(GameState::Loading);
-        }
+    let touched = transition_query.iter()
+        .find(|(hits, _)| hits.iter().any(|data| player_query.get(data.entity).unwrap_or(false)));
+
+    if let Some((_, transition)) = touched {
+        exit.leave(transition.0);
     }
 }
 
@@ -0,0 +1,311 @@
+//! Keys, and the doors that stay shut until the player holds the matching one
+use std::collections::BTreeMap;
+
+use bevy::{prelude::*, utils::HashSet};
+use bevy_xpbd_2d::{prelude::*, math::*};
+use bevy_yoleck::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameplaySet, GameState, player::Player};
+
+const KEY_SIZE: Vec2 = Vec2::new(12., 20.);
+/// How visible an open door still is
+const OPEN_DOOR_ALPHA: f32 = 0.25;
+
+// COMPONENTS
+#[derive(Component)]
+pub struct Key {
+    id: String,
+    color: Color,
+}
+
+#[derive(Component)]
+pub struct Door {
+    id: String,
+    color: Color,
+}
+
+impl Door {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+#[derive(Component)]
+pub struct HeldKeysHud;
+
+// RESOURCES
+/// The keys the player has picked up this attempt, by id, cleared whenever a level is loaded
+#[derive(Resource, Default)]
+pub struct HeldKeys(BTreeMap<String, Color>);
+
+/// The ids of doors held open by switches this frame, whether or not the player has their key
+#[derive(Resource, Default)]
+pub struct OpenedDoors(pub HashSet<String>);
+
+// BUNDLES
+#[derive(Bundle)]
+pub struct KeyBundle {
+    sprite: SpriteBundle,
+    trigger: ShapeCaster,
+    key: Key,
+}
+
+impl KeyBundle {
+    pub fn new(position: Vec2, id: String, color: Color) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(KEY_SIZE),
+                    color,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(1.)),
+                ..Default::default()
+            },
+            trigger: ShapeCaster::new(Collider::cuboid(KEY_SIZE.x, KEY_SIZE.y), Vector::new(position.x, position.y), 0.0, Vector::ONE)
+                .with_max_time_of_impact(10.)
+                .with_max_hits(8),
+            key: Key { id, color },
+        }
+    }
+}
+
+#[derive(Bundle)]
+pub struct DoorBundle {
+    sprite: SpriteBundle,
+    body: RigidBody,
+    collider: Collider,
+    door: Door,
+}
+
+impl DoorBundle {
+    pub fn new(position: Vec2, size: Vec2, id: String, color: Color) -> Self {
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    color,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(0.2)),
+                ..Default::default()
+            },
+            body: RigidBody::Static,
+            collider: Collider::cuboid(size.x, size.y),
+            door: Door { id, color },
+        }
+    }
+}
+
+// SYSTEMS
+fn collect_keys(
+    keys: Query<(&Key, &ShapeHits)>,
+    player_query: Query<(), With<Player>>,
+    mut held: ResMut<HeldKeys>,
+) {
+    for (key, hits) in keys.iter() {
+        if !held.0.contains_key(&key.id) && hits.iter().any(|data| player_query.contains(data.entity)) {
+            held.0.insert(key.id.clone(), key.color);
+        }
+    }
+}
+
+fn drop_keys(mut held: ResMut<HeldKeys>, mut opened: ResMut<OpenedDoors>) {
+    *held = HeldKeys::default();
+    opened.0.clear();
+}
+
+/// Hides collected keys, and lets the player through doors they hold the key for or that are held open
+fn update_locks(
+    mut cmd: Commands,
+    held: Res<HeldKeys>,
+    opened: Res<OpenedDoors>,
+    mut keys: Query<(&Key, &mut Visibility)>,
+    mut doors: Query<(Entity, &Door, &mut Sprite, Has<Sensor>)>,
+) {
+    for (key, mut visibility) in keys.iter_mut() {
+        let target = if held.0.contains_key(&key.id) { Visibility::Hidden } else { Visibility::Inherited };
+        if *visibility != target {
+            *visibility = target;
+        }
+    }
+
+    for (entity, door, mut sprite, open) in doors.iter_mut() {
+        let unlocked = held.0.contains_key(&door.id) || opened.0.contains(&door.id);
+        if unlocked == open {
+            continue;
+        }
+
+        if unlocked {
+            cmd.entity(entity).insert(Sensor);
+            sprite.color = door.color.with_a(OPEN_DOOR_ALPHA);
+        } else {
+            cmd.entity(entity).remove::<Sensor>();
+            sprite.color = door.color;
+        }
+    }
+}
+
+fn setup_ui(mut cmd: Commands) {
+    cmd.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.),
+            right: Val::Px(8.),
+            column_gap: Val::Px(4.),
+            ..Default::default()
+        },
+        ..Default::default()
+    }, HeldKeysHud));
+}
+
+fn rebuild_ui(mut cmd: Commands, held: Res<HeldKeys>, hud_query: Query<Entity, With<HeldKeysHud>>) {
+    let Ok(hud) = hud_query.get_single() else { return };
+
+    cmd.entity(hud)
+        .despawn_descendants()
+        .with_children(|parent| {
+            for &color in held.0.values() {
+                parent.spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(KEY_SIZE.x),
+                        height: Val::Px(KEY_SIZE.y),
+                        ..Default::default()
+                    },
+                    background_color: color.into(),
+                    ..Default::default()
+                });
+            }
+        });
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct YoleckKey {
+    id: String,
+    color: Color,
+    x: Scalar,
+    y: Scalar,
+}
+
+impl Default for YoleckKey {
+    fn default() -> Self {
+        Self {
+            id: String::from("Gold"),
+            color: Color::GOLD,
+            x: 0.,
+            y: 0.,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct YoleckDoor {
+    id: String,
+    color: Color,
+    width: Scalar,
+    height: Scalar,
+    x: Scalar,
+    y: Scalar,
+}
+
+impl Default for YoleckDoor {
+    fn default() -> Self {
+        Self {
+            id: String::from("Gold"),
+            color: Color::GOLD,
+            width: 25.,
+            height: 100.,
+            x: 0.,
+            y: 0.,
+        }
+    }
+}
+
+/// The id and colour controls shared by keys and doors
+fn edit_lock_id(ui: &mut egui::Ui, id: &mut String, color: &mut Color) {
+    ui.horizontal(|ui| {
+        ui.label("ID: ");
+        ui.text_edit_singleline(id);
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("Colour: ");
+        let mut rgba = color.as_rgba_f32();
+        if ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed() {
+            *color = Color::rgba(rgba[0], rgba[1], rgba[2], rgba[3]);
+        }
+    });
+}
+
+fn populate_key(mut pop: YoleckPopulate<&YoleckKey>) {
+    pop.populate(|_ctx, mut cmd, key| {
+        cmd.insert(KeyBundle::new(Vec2::new(key.x, key.y), key.id.clone(), key.color));
+    })
+}
+
+fn edit_key(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut YoleckKey>) {
+    if let Ok(mut key) = edit.get_single_mut() {
+        let key = &mut *key;
+        edit_lock_id(&mut ui, &mut key.id, &mut key.color);
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut key.x).speed(1.).fixed_decimals(0).prefix("X: "));
+            ui.add(egui::DragValue::new(&mut key.y).speed(1.).fixed_decimals(0).prefix("Y: "));
+        });
+    }
+}
+
+fn populate_door(mut pop: YoleckPopulate<&YoleckDoor>) {
+    pop.populate(|_ctx, mut cmd, door| {
+        cmd.insert(DoorBundle::new(
+            Vec2::new(door.x, door.y),
+            Vec2::new(door.width, door.height),
+            door.id.clone(),
+            door.color,
+        ));
+    })
+}
+
+fn edit_door(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut YoleckDoor>) {
+    if let Ok(mut door) = edit.get_single_mut() {
+        let door = &mut *door;
+        edit_lock_id(&mut ui, &mut door.id, &mut door.color);
+
+        ui.add(egui::Slider::new(&mut door.width, 10.0..=500.0).prefix("Width: "));
+        ui.add(egui::Slider::new(&mut door.height, 10.0..=500.0).prefix("Height: "));
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut door.x).speed(1.).fixed_decimals(0).prefix("X: "));
+            ui.add(egui::DragValue::new(&mut door.y).speed(1.).fixed_decimals(0).prefix("Y: "));
+        });
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<HeldKeys>()
+            .init_resource::<OpenedDoors>()
+            .add_systems(Startup, setup_ui)
+            .add_systems(OnEnter(GameState::LevelEditor), drop_keys)
+            .add_systems(OnEnter(GameState::Menu), drop_keys)
+            .add_systems(Update, (
+                collect_keys.in_set(GameplaySet::Update),
+                update_locks,
+                rebuild_ui.run_if(resource_changed::<HeldKeys>()),
+            ));
+
+        app.add_yoleck_entity_type(YoleckEntityType::new("Key").with::<YoleckKey>());
+        app.add_yoleck_edit_system(edit_key);
+        app.yoleck_populate_schedule_mut().add_systems(populate_key);
+
+        app.add_yoleck_entity_type(YoleckEntityType::new("Door").with::<YoleckDoor>());
+        app.add_yoleck_edit_system(edit_door);
+        app.yoleck_populate_schedule_mut().add_systems(populate_door);
+    }
+}
@@ -1,28 +1,100 @@
-use bevy::{prelude::*, utils::HashMap, input::mouse::{MouseWheel, MouseScrollUnit}};
-use bevy_xpbd_2d::{prelude::*, math::Scalar};
-use bevy_yoleck::prelude::*;
+use std::collections::BTreeSet;
+
+use bevy::{prelude::*, utils::HashMap, input::mouse::{MouseWheel, MouseScrollUnit}, ecs::{query::WorldQuery, system::SystemParam}};
+use bevy_xpbd_2d::{prelude::*, math::{Scalar, Vector}};
+use bevy_yoleck::{prelude::*, YoleckManaged};
 use serde::{Deserialize, Serialize};
-use strum::{EnumIter, IntoEnumIterator};
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
+
+use crate::{GameplaySet, GameState, PauseState, input::{Action, Actions}, save::UserSettings, player::{Player, respawn::{check_squished, Respawn, Squished}}};
 
-use crate::{GameplaySet, EditorSet, GameState};
+use super::{LevelLoaded, LevelRun, hazard::Hazard, settings::{CrushBehaviour, LevelSettings}};
 
 
 // COMPONENTS
-#[derive(Component, PartialEq, Eq, Hash, Clone, Copy, EnumIter, Serialize, Deserialize, Debug, PartialOrd, Ord)]
-#[repr(u8)]
-pub enum ScaleGroup {
-    Red, Green, Blue,
+/// The identifier of a scale group, shared by the group itself and every object in it
+#[derive(Component, PartialEq, Eq, Hash, Clone, Serialize, Deserialize, Debug, PartialOrd, Ord, Default)]
+#[serde(transparent)]
+pub struct ScaleGroup(pub String);
+
+/// The level-defined properties of a scale group
+#[derive(Component)]
+pub struct ScaleGroupInfo {
+    name: String,
+    color: Color,
+    order: i32,
+    start: Scalar,
+    bounds: std::ops::RangeInclusive<Scalar>,
+    step: Scalar,
+}
+
+impl ScaleGroupInfo {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn bounds(&self) -> &std::ops::RangeInclusive<Scalar> {
+        &self.bounds
+    }
+
+    /// The amount a single keyboard press changes this group by
+    fn nudge(&self) -> Scalar {
+        if self.step > 0. { self.step } else { 0.1 }
+    }
+
+    /// The nudge while holding the fine scaling modifier, which can't be less than a step
+    fn fine_nudge(&self) -> Scalar {
+        if self.step > 0. { self.step } else { 0.01 }
+    }
+
+    /// Clamps a scale to this group's range, snapping it to the nearest step above the minimum
+    fn snap(&self, value: Scalar) -> Scalar {
+        let (min, max) = (*self.bounds.start(), *self.bounds.end());
+        let value = value.clamp(min, max);
+        if self.step <= 0. {
+            return value;
+        }
+
+        let steps = ((value - min) / self.step).round().min(((max - min) / self.step).floor());
+        min + steps * self.step
+    }
 }
 
+/// Marks a scale group the player can't currently change
+#[derive(Component)]
+pub struct Locked;
+
+/// Marks one of the default groups given to levels that don't define any
+#[derive(Component)]
+pub struct FallbackGroup;
+
+#[derive(Resource, Default)]
+pub struct SelectedGroup(Option<ScaleGroup>);
+
+impl SelectedGroup {
+    pub fn get(&self) -> Option<&ScaleGroup> {
+        self.0.as_ref()
+    }
+}
+
+/// How scalable objects move towards their target size
 #[derive(Resource)]
-pub struct SelectedGroup(ScaleGroup);
-
-impl From<ScaleGroup> for Color {
-    fn from(group: ScaleGroup) -> Self {
-        match group {
-            ScaleGroup::Red => Color::RED,
-            ScaleGroup::Green => Color::DARK_GREEN,
-            ScaleGroup::Blue => Color::BLUE,
+pub struct ScaleTweening {
+    /// How quickly objects ease towards their target, per second
+    pub speed: f32,
+    /// Jump straight to the target instead, for debugging
+    pub instant: bool,
+}
+
+impl Default for ScaleTweening {
+    fn default() -> Self {
+        Self {
+            speed: 12.,
+            instant: false,
         }
     }
 }
@@ -30,21 +102,175 @@ impl From<ScaleGroup> for Color {
 #[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, EnumIter)]
 #[repr(u8)]
 pub enum ScaleDirection {
-    Up, Down, Left, Right
+    Up, Down, Left, Right,
+    Vertical, Horizontal, Centre,
+    UpLeft, UpRight, DownLeft, DownRight,
+}
+
+impl ScaleDirection {
+    /// The side each axis grows towards, or `None` if the axis doesn't scale. `0.` grows from the centre.
+    fn anchors(self) -> (Option<Scalar>, Option<Scalar>) {
+        match self {
+            ScaleDirection::Up => (None, Some(1.)),
+            ScaleDirection::Down => (None, Some(-1.)),
+            ScaleDirection::Left => (Some(-1.), None),
+            ScaleDirection::Right => (Some(1.), None),
+            ScaleDirection::Vertical => (None, Some(0.)),
+            ScaleDirection::Horizontal => (Some(0.), None),
+            ScaleDirection::Centre => (Some(0.), Some(0.)),
+            ScaleDirection::UpLeft => (Some(-1.), Some(1.)),
+            ScaleDirection::UpRight => (Some(1.), Some(1.)),
+            ScaleDirection::DownLeft => (Some(-1.), Some(-1.)),
+            ScaleDirection::DownRight => (Some(1.), Some(-1.)),
+        }
+    }
+
+    fn is_two_axis(self) -> bool {
+        matches!(self.anchors(), (Some(_), Some(_)))
+    }
+}
+
+/// Maps a group's scale onto the scale an object responds with, before its factor is applied
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, Default, EnumIter, IntoStaticStr)]
+pub enum ScaleCurve {
+    #[default]
+    Linear,
+    Inverse,
+    Quadratic,
+    /// Jumps to the value of the highest threshold the scale has reached
+    Stepped(Vec<Vec2>),
+    /// Interpolates linearly between `(scale, value)` points
+    Table(Vec<Vec2>),
+}
+
+impl ScaleCurve {
+    fn sorted(mut self) -> Self {
+        if let ScaleCurve::Stepped(points) | ScaleCurve::Table(points) = &mut self {
+            points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        }
+        self
+    }
+
+    fn apply(&self, scale: Scalar) -> Scalar {
+        match self {
+            ScaleCurve::Linear => scale,
+            ScaleCurve::Inverse => 1. / scale.max(Scalar::EPSILON),
+            ScaleCurve::Quadratic => scale * scale,
+            ScaleCurve::Stepped(points) => points.iter()
+                .rev()
+                .find(|point| point.x <= scale)
+                .or(points.first())
+                .map_or(scale, |point| point.y),
+            ScaleCurve::Table(points) => {
+                let (Some(first), Some(last)) = (points.first(), points.last()) else { return scale };
+                match points.iter().position(|point| point.x > scale) {
+                    Some(0) => first.y,
+                    Some(i) => {
+                        let (a, b) = (points[i - 1], points[i]);
+                        a.y + (b.y - a.y) * (scale - a.x) / (b.x - a.x)
+                    },
+                    None => last.y,
+                }
+            },
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct Scalable {
-    factor: Scalar,
+    factor: Vec2,
+    curve: ScaleCurve,
     direction: ScaleDirection,
     position: Vec2,
     size: Vec2,
     bounds: std::ops::RangeInclusive<Scalar>,
 }
 
+impl Scalable {
+    /// Computes the scale and centre of this object when its group is at `group_scale`
+    fn target(&self, group_scale: Scalar) -> (Vec2, Vec2) {
+        let group_scale = self.curve.apply(group_scale);
+        let axis_scale = |factor: Scalar| (1. + ((group_scale - 1.) * factor)).clamp(*self.bounds.start(), *self.bounds.end());
+        let (x, y) = self.direction.anchors();
+
+        let mut scale = Vec2::ONE;
+        let mut position = self.position;
+
+        if let Some(sign) = x {
+            scale.x = axis_scale(self.factor.x);
+            position.x += sign * (scale.x - 1.) * self.size.x / 2.;
+        }
+
+        if let Some(sign) = y {
+            scale.y = axis_scale(self.factor.y);
+            position.y += sign * (scale.y - 1.) * self.size.y / 2.;
+        }
+
+        (scale, position)
+    }
+}
+
+/// The size and centre an object is currently at, which lag behind its target while easing
+#[derive(Component)]
+pub struct ScalableMotion {
+    scale: Vec2,
+    position: Vec2,
+}
+
+/// The current factor of a scale group, where `1.` leaves its objects at their original size
 #[derive(Component)]
 pub struct Scale(Scalar);
 
+impl Scale {
+    pub fn get(&self) -> Scalar {
+        self.0
+    }
+}
+
+/// The scale a group would have without snapping, so that small inputs can accumulate into a step
+#[derive(Component)]
+pub struct UnsnappedScale(Scalar);
+
+/// The last scale of a group that didn't squish the player
+#[derive(Component)]
+pub struct SafeScale(Scalar);
+
+/// The scale of a group, for systems that move it directly rather than through player input
+#[derive(WorldQuery)]
+#[world_query(mutable)]
+pub struct ScaleGroupState {
+    pub group: &'static ScaleGroup,
+    info: &'static ScaleGroupInfo,
+    scale: &'static mut Scale,
+    unsnapped: &'static mut UnsnappedScale,
+    safe: &'static mut SafeScale,
+}
+
+impl ScaleGroupStateItem<'_> {
+    pub fn scale(&self) -> Scalar {
+        self.scale.0
+    }
+
+    /// Moves the group straight to `value`, snapped to its range and step
+    pub fn set(&mut self, value: Scalar) {
+        let value = self.info.snap(value);
+        self.scale.0 = value;
+        self.unsnapped.0 = value;
+        self.safe.0 = value;
+    }
+
+    /// Moves the group to `value` without marking it safe, so the level's crush rules still settle it
+    pub fn move_to(&mut self, value: Scalar) {
+        let value = self.info.snap(value);
+        self.scale.0 = value;
+        self.unsnapped.0 = value;
+    }
+
+    pub fn reset(&mut self) {
+        self.set(self.info.start);
+    }
+}
+
 // EVENTS
 #[derive(Event)]
 pub struct SelectFactorEvent {
@@ -52,15 +278,46 @@ pub struct SelectFactorEvent {
 }
 
 #[derive(Event)]
-pub struct ChangeScaleEvent(Scalar);
+pub struct ChangeScaleEvent(pub Scalar);
+
+/// Sets the selected group to an exact scale, still subject to its range, step and the level's crush rules
+#[derive(Event)]
+pub struct SetScaleEvent(pub Scalar);
+
+/// Sent when a solid group stopped short of the requested scale to avoid the player
+#[derive(Event)]
+pub struct ScaleBlockedEvent(ScaleGroup);
 
 // BUNDLES
+#[derive(Bundle)]
+pub struct ScaleGroupBundle {
+    group: ScaleGroup,
+    info: ScaleGroupInfo,
+    scale: Scale,
+    unsnapped: UnsnappedScale,
+    safe: SafeScale,
+}
+
+impl ScaleGroupBundle {
+    pub fn new(group: ScaleGroup, info: ScaleGroupInfo) -> Self {
+        Self {
+            group,
+            scale: Scale(info.snap(info.start)),
+            unsnapped: UnsnappedScale(info.start),
+            safe: SafeScale(info.snap(info.start)),
+            info,
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct ScalableBundle {
     sprite: SpriteBundle,
     body: RigidBody,
+    velocity: LinearVelocity,
     collider: Collider,
     scalable: Scalable,
+    motion: ScalableMotion,
     group: ScaleGroup,
 }
 
@@ -69,7 +326,6 @@ impl ScalableBundle {
         Self {
             sprite: SpriteBundle {
                 sprite: Sprite {
-                    color: group.into(),
                     custom_size: Some(size),
                     ..Default::default()
                 },
@@ -79,22 +335,125 @@ impl ScalableBundle {
                 },
                 ..Default::default()
             },
-            body: RigidBody::Static,
+            body: RigidBody::Kinematic,
+            velocity: LinearVelocity::default(),
             collider: Collider::cuboid(size.x, size.y),
+            motion: ScalableMotion {
+                scale: Vec2::ONE,
+                position: scalable.position,
+            },
             scalable, group,
         }
     }
 }
 
+// SYSTEM PARAMS
+/// The level's crush rules, and whether moving a group would land its objects on the player
+#[derive(SystemParam)]
+pub struct ScalingRules<'w, 's> {
+    settings: Query<'w, 's, &'static LevelSettings>,
+    spatial_query: SpatialQuery<'w, 's>,
+    player_query: Query<'w, 's, Entity, With<Player>>,
+    scalable_objects: Query<'w, 's, (&'static Scalable, &'static ScaleGroup), Without<Hazard>>,
+}
+
+impl ScalingRules<'_, '_> {
+    pub fn crush(&self) -> CrushBehaviour {
+        self.settings.get_single().map(|settings| settings.crush).unwrap_or_default()
+    }
+
+    /// Checks whether any object in `group` would overlap the player with the group at `group_scale`
+    pub fn intersects_player(&self, group: &ScaleGroup, group_scale: Scalar) -> bool {
+        let Ok(player) = self.player_query.get_single() else { return false };
+
+        self.scalable_objects.iter()
+            .filter(|(_, object_group)| *object_group == group)
+            .any(|(scalable, _)| {
+                let (scale, position) = scalable.target(group_scale);
+                let size = (scalable.size * scale - Vec2::splat(SOLID_MARGIN * 2.)).max(Vec2::ONE);
+
+                self.spatial_query
+                    .shape_intersections(&Collider::cuboid(size.x, size.y), position, 0., SpatialQueryFilter::default())
+                    .contains(&player)
+            })
+    }
+}
+
+/// Counts the player's scale changes for the level's statistics, once per gesture rather than once per frame
+#[derive(SystemParam)]
+struct ScaleChangeCounter<'w, 's> {
+    time: Res<'w, Time>,
+    run: ResMut<'w, LevelRun>,
+    /// The group changed last, and when
+    last_change: Local<'s, Option<(ScaleGroup, f32)>>,
+}
+
+impl ScaleChangeCounter<'_, '_> {
+    fn count(&mut self, group: &ScaleGroup) {
+        let now = self.time.elapsed_seconds();
+        match &mut *self.last_change {
+            Some((last_group, at)) if last_group == group && now - *at < GESTURE_GAP => *at = now,
+            _ => {
+                *self.last_change = Some((group.clone(), now));
+                self.run.scale_changes += 1;
+            },
+        }
+    }
+}
+
+// HELPERS
+/// The groups every level had before levels could define their own, for level files that still rely on them
+const FALLBACK_GROUPS: [(&str, Color); 3] = [("Red", Color::RED), ("Green", Color::DARK_GREEN), ("Blue", Color::BLUE)];
+
+/// How far a solid object may overlap the player without blocking, so resting contacts don't count
+const SOLID_MARGIN: Scalar = 2.;
+
+
+/// How fast an object's edge may move, in pixels per second, so physics pushes the player instead of tunnelling through them
+const MAX_SWEEP_SPEED: f32 = 240.;
+/// How close an object's edges must be to their target to stop easing
+const TWEEN_EPSILON: f32 = 0.01;
+
+/// Eases an object of `size` from its `current` scale and position towards its `target` by `ease`,
+/// moving no edge further than `max_distance`
+fn tween(size: Vec2, current: (Vec2, Vec2), target: (Vec2, Vec2), ease: f32, max_distance: f32) -> (Vec2, Vec2) {
+    // Edges move linearly with the interpolation factor, so limiting it limits them
+    let edge_distance = |t: f32| {
+        let (scale, position) = (current.0.lerp(target.0, t), current.1.lerp(target.1, t));
+        ((position - current.1).abs() + (size * (scale - current.0)).abs() / 2.).max_element()
+    };
+
+    if edge_distance(1.) < TWEEN_EPSILON {
+        return target;
+    }
+
+    let mut t = ease;
+    let distance = edge_distance(t);
+    if distance > max_distance {
+        t *= max_distance / distance;
+    }
+
+    (current.0.lerp(target.0, t), current.1.lerp(target.1, t))
+}
+
+/// Sorts the groups of the current level into the order they are shown to the player
+pub(super) fn ordered_groups<'a>(
+    groups: impl Iterator<Item = (&'a ScaleGroup, &'a ScaleGroupInfo)>,
+) -> Vec<(&'a ScaleGroup, &'a ScaleGroupInfo)> {
+    let mut groups = groups.collect::<Vec<_>>();
+    groups.sort_by(|a, b| a.1.order.cmp(&b.1.order).then_with(|| a.0.cmp(b.0)));
+    groups
+}
+
 // SYSTEMS
-fn handle_keyboard_input(keyboard_input: Res<Input<KeyCode>>, mut select_factor_evw: EventWriter<SelectFactorEvent>) {
+fn handle_keyboard_input(actions: Actions, mut select_factor_evw: EventWriter<SelectFactorEvent>) {
     let mut direction = 0i8;
 
-    if keyboard_input.any_just_pressed([KeyCode::W, KeyCode::Up]) {
+    if actions.just_pressed(Action::NextGroup) {
         direction = 1; 
     }
 
-    if keyboard_input.any_just_pressed([KeyCode::S, KeyCode::Down]) {
+    if actions.just_pressed(Action::PrevGroup) {
         direction = -1; 
     }
 
@@ -103,6 +462,68 @@ fn handle_keyboard_input(keyboard_input: Res<Input<KeyCode>>, mut select_factor_
     }
 }
 
+/// How long a scale key must be held before it starts repeating, in seconds
+const REPEAT_DELAY: f32 = 0.3;
+/// Nudges per second when repeating starts, how quickly that grows while held, and its limit
+const REPEAT_RATE: f32 = 5.;
+const REPEAT_ACCELERATION: f32 = 15.;
+const MAX_REPEAT_RATE: f32 = 40.;
+/// How many nudges one press makes while holding the coarse scaling modifier
+const COARSE_NUDGES: Scalar = 5.;
+
+/// Nudges once when a scale key is pressed, then keeps scaling faster the longer it is held
+fn handle_keyboard_scaling(
+    actions: Actions,
+    time: Res<Time>,
+    selected: Res<SelectedGroup>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+    mut held: Local<Option<f32>>,
+    mut change_scale_evw: EventWriter<ChangeScaleEvent>,
+) {
+    let direction = actions.axis(Action::ScaleDown, Action::ScaleUp);
+    if direction == 0. {
+        *held = None;
+        return;
+    }
+
+    let Some((_, info)) = groups.iter().find(|(group, _)| Some(*group) == selected.0.as_ref()) else { return };
+    let nudge = if actions.pressed(Action::CoarseScale) {
+        info.nudge() * COARSE_NUDGES
+    } else if actions.pressed(Action::FineScale) {
+        info.fine_nudge()
+    } else {
+        info.nudge()
+    };
+
+    let Some(held_for) = *held else {
+        *held = Some(0.);
+        change_scale_evw.send(ChangeScaleEvent(direction * nudge));
+        return;
+    };
+
+    let held_for = held_for + time.delta_seconds();
+    *held = Some(held_for);
+    if held_for > REPEAT_DELAY {
+        let rate = (REPEAT_RATE + REPEAT_ACCELERATION * (held_for - REPEAT_DELAY)).min(MAX_REPEAT_RATE);
+        change_scale_evw.send(ChangeScaleEvent(direction * nudge * rate * time.delta_seconds()));
+    }
+}
+
+/// How much a fully held trigger changes the scale each second, before sensitivity
+const ANALOG_SCALE_RATE: Scalar = 1.;
+
+fn handle_analog_scaling(
+    actions: Actions,
+    settings: Res<UserSettings>,
+    time: Res<Time>,
+    mut change_scale_evw: EventWriter<ChangeScaleEvent>,
+) {
+    let rate = actions.analog_scale();
+    if rate != 0. {
+        change_scale_evw.send(ChangeScaleEvent(rate * settings.scale_sensitivity * ANALOG_SCALE_RATE * time.delta_seconds()));
+    }
+}
+
 fn handle_mouse_scrolling(mut mouse_scroll: EventReader<MouseWheel>, mut change_scale_evw: EventWriter<ChangeScaleEvent>) {
     for ev in mouse_scroll.read() {
         match ev.unit {
@@ -116,82 +537,370 @@ fn handle_mouse_scrolling(mut mouse_scroll: EventReader<MouseWheel>, mut change_
     }
 }
 
+/// Eases objects towards their target once per physics step, moving their growing edges by velocity so they carry and push the player
 fn apply_scale_factors(
-    mut scalable_objects: Query<(&mut Scalable, &mut Transform, &ScaleGroup)>,
+    delta_time: Res<DeltaTime>,
+    tweening: Res<ScaleTweening>,
+    mut scalable_objects: Query<(&Scalable, &mut ScalableMotion, &mut Transform, &mut Position, &mut LinearVelocity, &ScaleGroup)>,
     scales: Query<(&ScaleGroup, &Scale)>
 ) {
-    let scales = scales.iter().map(|(g, s)| (*g, s)).collect::<HashMap<ScaleGroup, &Scale>>();
-    for (scalable, mut transform, group) in scalable_objects.iter_mut() {
-        let scale_group = scales.get(group).unwrap().0;
-        let scale = (1. + (scale_group * scalable.factor)).clamp(*scalable.bounds.start(), *scalable.bounds.end());
-
-        let direction_sign = match scalable.direction {
-            ScaleDirection::Up | ScaleDirection::Right => 1.,
-            ScaleDirection::Down | ScaleDirection::Left => -1.,
+    let dt = delta_time.0;
+    let ease = 1. - (-tweening.speed * dt).exp();
+    let scales = scales.iter().collect::<HashMap<&ScaleGroup, &Scale>>();
+    for (scalable, mut motion, mut transform, mut body_position, mut velocity, group) in scalable_objects.iter_mut() {
+        let scale_group = scales.get(group).map_or(1., |scale| scale.0);
+        let target = scalable.target(scale_group);
+
+        // Objects that were just loaded start at their size rather than growing into it
+        let snap = tweening.instant || motion.is_added() || dt <= 0.;
+        let (scale, position) = if snap {
+            target
+        } else {
+            tween(scalable.size, (motion.scale, motion.position), target, ease, MAX_SWEEP_SPEED * dt)
         };
 
-        match scalable.direction {
-            ScaleDirection::Up | ScaleDirection::Down => {
-                transform.scale.y = scale;
-                let translation = direction_sign * (scale - 1.) * scalable.size.y / 2.;
-                transform.translation.y = scalable.position.y + translation;
-            },
-            ScaleDirection::Left | ScaleDirection::Right => {
-                transform.scale.x = scale;
-                let translation = direction_sign * (scale - 1.) * scalable.size.x / 2.;
-                transform.translation.x = scalable.position.x + translation;
-            }
-        }
+        // Only an edge growing away from an anchor has a single velocity; objects growing from their centre just resize
+        let moved = position - motion.position;
+        let grown = scalable.size * (scale - motion.scale);
+        let edge_velocity = |anchor: Option<Scalar>, moved: Scalar, grown: Scalar| match anchor {
+            Some(sign) if sign != 0. && !snap => (moved + sign * grown / 2.) / dt,
+            _ => 0.,
+        };
+        let (x, y) = scalable.direction.anchors();
+        velocity.0 = Vector::new(edge_velocity(x, moved.x, grown.x), edge_velocity(y, moved.y, grown.y));
+
+        motion.scale = scale;
+        motion.position = position;
+
+        // This step moves the body by its velocity, so start it that far back for it to end up at `position`
+        transform.scale = scale.extend(transform.scale.z);
+        body_position.0 = position - velocity.0 * dt;
     }
 }
 
 fn update_selection(
     mut selected: ResMut<SelectedGroup>,
     mut evr: EventReader<SelectFactorEvent>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
 ) {
-    let mut selections = ScaleGroup::iter().cycle();
+    let groups = ordered_groups(groups.iter());
+    if groups.is_empty() {
+        evr.clear();
+        selected.0 = None;
+        return;
+    }
+
+    let mut index = selected.0.as_ref()
+        .and_then(|selected| groups.iter().position(|(group, _)| *group == selected))
+        .unwrap_or(0);
 
     for ev in evr.read() {
-        selected.0 = match ev.direction {
-            1 => selections.nth(usize::from(selected.0 as u8 + 1)).unwrap(),
-            -1 => selections.nth(usize::from(selected.0 as u8 + ScaleGroup::Blue as u8)).unwrap(),
-            _ => unreachable!()
-        };
+        index = (index as isize + ev.direction as isize).rem_euclid(groups.len() as isize) as usize;
+    }
+
+    if selected.0.as_ref() != Some(groups[index].0) {
+        selected.0 = Some(groups[index].0.clone());
     }
 }
 
-fn update_scale(selected: Res<SelectedGroup>, mut q: Query<(&mut Scale, &ScaleGroup)>, mut change_scale_evr: EventReader<ChangeScaleEvent>) {
-    let mut scale = q.iter_mut().find(|it| *it.1 == selected.0).unwrap().0;
+fn update_scale(
+    selected: Res<SelectedGroup>,
+    mut q: Query<(&mut Scale, &mut UnsnappedScale, &ScaleGroup, &ScaleGroupInfo, Has<Locked>)>,
+    mut change_scale_evr: EventReader<ChangeScaleEvent>,
+    mut set_scale_evr: EventReader<SetScaleEvent>,
+    mut blocked_evw: EventWriter<ScaleBlockedEvent>,
+    rules: ScalingRules,
+    mut counter: ScaleChangeCounter,
+) {
+    let Some((mut scale, mut unsnapped, group, info, locked)) = q.iter_mut().find(|it| Some(it.2) == selected.0.as_ref()) else {
+        change_scale_evr.clear();
+        set_scale_evr.clear();
+        return
+    };
+
+    if locked {
+        if change_scale_evr.read().count() + set_scale_evr.read().count() > 0 {
+            blocked_evw.send(ScaleBlockedEvent(group.clone()));
+        }
+        return;
+    }
 
     for ev in change_scale_evr.read() {
-        scale.0 += ev.0;
+        unsnapped.0 += ev.0;
     }
 
-    scale.0 = scale.0.clamp(-0.9, 4.);
+    // An exact scale replaces whatever partial steps had built up
+    if let Some(ev) = set_scale_evr.read().last() {
+        unsnapped.0 = ev.0;
+    }
+
+    unsnapped.0 = unsnapped.0.clamp(*info.bounds.start(), *info.bounds.end());
+    let mut snapped = info.snap(unsnapped.0);
+    if scale.0 == snapped {
+        return;
+    }
+
+    if rules.crush() == CrushBehaviour::Solid {
+        let is_legal = |group_scale| !rules.intersects_player(group, group_scale);
+
+        // Never block a group that is already overlapping, since the player couldn't get out otherwise
+        if is_legal(scale.0) && !is_legal(snapped) {
+            let mut legal = scale.0;
+            if info.step > 0. {
+                // Step back from the requested scale until the objects fit
+                let direction = (snapped - scale.0).signum();
+                let mut candidate = snapped - direction * info.step;
+                while (candidate - scale.0) * direction > 0. && !is_legal(candidate) {
+                    candidate -= direction * info.step;
+                }
+
+                if (candidate - scale.0) * direction > 0. {
+                    legal = candidate;
+                }
+            } else {
+                let mut illegal = snapped;
+                for _ in 0..10 {
+                    let middle = (legal + illegal) / 2.;
+                    if is_legal(middle) {
+                        legal = middle;
+                    } else {
+                        illegal = middle;
+                    }
+                }
+            }
+
+            snapped = legal;
+            unsnapped.0 = legal;
+            blocked_evw.send(ScaleBlockedEvent(group.clone()));
+        }
+    }
+
+    if scale.0 != snapped {
+        scale.0 = snapped;
+        counter.count(group);
+    }
 }
 
-fn setup_groups(mut cmd: Commands) {
-    cmd.spawn_batch(ScaleGroup::iter().map(|it| (it, Scale(0.))));
-    cmd.insert_resource(SelectedGroup(ScaleGroup::Red));
+/// A scale only becomes safe once its objects have finished easing to it without squishing the player
+fn settle_scales(
+    settings: Query<&LevelSettings>,
+    mut squished_evr: EventReader<Squished>,
+    mut groups: Query<(&ScaleGroup, &mut Scale, &mut UnsnappedScale, &mut SafeScale)>,
+    scalable_objects: Query<(&Scalable, &ScalableMotion, &ScaleGroup)>,
+) {
+    let squished = squished_evr.read().count() > 0;
+    let refuse = settings.get_single().map(|settings| settings.crush).unwrap_or_default() == CrushBehaviour::Refuse;
+
+    for (group, mut scale, mut unsnapped, mut safe) in groups.iter_mut() {
+        let settled = scalable_objects.iter()
+            .filter(|(_, _, object_group)| *object_group == group)
+            .all(|(scalable, motion, _)| (motion.scale, motion.position) == scalable.target(scale.0));
+
+        if !squished {
+            if settled {
+                safe.0 = scale.0;
+            }
+        } else if refuse && scale.0 != safe.0 {
+            scale.0 = safe.0;
+            unsnapped.0 = safe.0;
+        }
+    }
+}
+
+/// Gives a level without any groups of its own the fallback groups, and takes them away once it has some
+pub(super) fn update_fallback_groups(
+    mut cmd: Commands,
+    level_entities: Query<(), With<YoleckManaged>>,
+    level_groups: Query<(), (With<ScaleGroupInfo>, Without<FallbackGroup>)>,
+    fallback_groups: Query<Entity, With<FallbackGroup>>,
+) {
+    let needed = !level_entities.is_empty() && level_groups.is_empty();
+
+    if needed && fallback_groups.is_empty() {
+        for (order, (name, color)) in FALLBACK_GROUPS.into_iter().enumerate() {
+            let info = ScaleGroupInfo {
+                name: String::from(name),
+                color,
+                order: order as i32,
+                start: 1.,
+                bounds: 0.1..=5.,
+                step: 0.,
+            };
+            cmd.spawn((ScaleGroupBundle::new(ScaleGroup(String::from(name)), info), FallbackGroup));
+        }
+    } else if !needed {
+        for entity in fallback_groups.iter() {
+            cmd.entity(entity).despawn();
+        }
+    }
+}
+
+/// Level files fail silently when an object names a group that doesn't exist, so say so when one loads
+fn check_unknown_groups(
+    mut loaded_evr: EventReader<LevelLoaded>,
+    level_groups: Query<&ScaleGroup, (With<ScaleGroupInfo>, Without<FallbackGroup>)>,
+    scalable_objects: Query<&ScaleGroup, With<Scalable>>,
+) {
+    if loaded_evr.read().count() == 0 {
+        return;
+    }
+
+    let mut known = level_groups.iter().map(|group| group.0.as_str()).collect::<Vec<_>>();
+    if known.is_empty() {
+        known.extend(FALLBACK_GROUPS.iter().map(|(name, _)| *name));
+    }
+
+    let unknown = scalable_objects.iter()
+        .filter(|group| !known.contains(&group.0.as_str()))
+        .collect::<BTreeSet<_>>();
+    for group in unknown {
+        warn!("Level has objects in scale group {:?}, which it doesn't define", group.0);
+    }
+}
+
+fn update_group_colors(
+    mut scalable_objects: Query<(&mut Sprite, &ScaleGroup), (With<Scalable>, Without<Hazard>)>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+) {
+    let colors = groups.iter().map(|(g, info)| (g, info.color)).collect::<HashMap<&ScaleGroup, Color>>();
+    for (mut sprite, group) in scalable_objects.iter_mut() {
+        let color = colors.get(group).copied().unwrap_or(Color::GRAY);
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+fn reset_scales(mut q: Query<ScaleGroupState>, mut history: ResMut<ScaleHistory>) {
+    for mut group in q.iter_mut() {
+        group.reset();
+    }
+    *history = ScaleHistory::default();
+}
+
+// HISTORY
+/// How long after a change to a group another change still counts as part of the same gesture, in seconds.
+/// Also used to count scale changes for the level's statistics
+const GESTURE_GAP: f32 = 0.5;
+
+struct HistoryEntry {
+    group: ScaleGroup,
+    from: Scalar,
+    to: Scalar,
+    /// When the gesture was last added to, in seconds since startup
+    at: f32,
+}
+
+/// Committed scale changes that can be undone and redone
+#[derive(Resource, Default)]
+pub struct ScaleHistory {
+    undo: Vec<HistoryEntry>,
+    redo: Vec<HistoryEntry>,
+    /// The last committed scale of each group, to spot new changes
+    committed: HashMap<ScaleGroup, Scalar>,
+}
+
+impl ScaleHistory {
+    /// Accepts a scale set by the level rather than the player, so it can't be undone
+    pub fn commit(&mut self, group: &ScaleGroup, scale: Scalar) {
+        self.committed.insert(group.clone(), scale);
+    }
+}
+
+/// Runs once scales have settled, so changes refused for squishing the player are never recorded
+fn record_history(
+    time: Res<Time>,
+    groups: Query<(&ScaleGroup, &SafeScale)>,
+    mut respawn_evr: EventReader<Respawn>,
+    mut history: ResMut<ScaleHistory>,
+) {
+    // Respawning restores scales from a checkpoint, which isn't the player's change to undo
+    let respawned = respawn_evr.read().count() > 0;
+    let now = time.elapsed_seconds();
+    let history = &mut *history;
+
+    for (group, safe) in groups.iter() {
+        let Some(from) = history.committed.insert(group.clone(), safe.0) else { continue };
+        if respawned || from == safe.0 {
+            continue;
+        }
+
+        history.redo.clear();
+        match history.undo.last_mut() {
+            Some(last) if last.group == *group && now - last.at < GESTURE_GAP => {
+                last.to = safe.0;
+                last.at = now;
+            },
+            _ => history.undo.push(HistoryEntry { group: group.clone(), from, to: safe.0, at: now }),
+        }
+    }
 }
 
-fn reset_scales(mut q: Query<&mut Scale, With<ScaleGroup>>) {
-    for mut scale in q.iter_mut() {
-        scale.0 = 0.;
+fn handle_history_input(
+    actions: Actions,
+    rules: ScalingRules,
+    mut groups: Query<ScaleGroupState>,
+    locked: Query<&ScaleGroup, With<Locked>>,
+    mut history: ResMut<ScaleHistory>,
+    mut blocked_evw: EventWriter<ScaleBlockedEvent>,
+) {
+    if !actions.pressed(Action::HistoryModifier) {
+        return;
     }
+
+    let history = &mut *history;
+    let undo = actions.just_pressed(Action::Undo);
+    let (from_stack, to_stack) = if undo {
+        (&mut history.undo, &mut history.redo)
+    } else if actions.just_pressed(Action::Redo) {
+        (&mut history.redo, &mut history.undo)
+    } else {
+        return;
+    };
+
+    let Some(entry) = from_stack.pop() else { return };
+    let Some(mut state) = groups.iter_mut().find(|state| *state.group == entry.group) else { return };
+    let target = if undo { entry.from } else { entry.to };
+
+    if locked.iter().any(|group| *group == entry.group) {
+        blocked_evw.send(ScaleBlockedEvent(entry.group.clone()));
+        from_stack.push(entry);
+        return;
+    }
+
+    // Undoing skips the usual scaling checks, so only crushing modes may land on the player
+    if rules.crush() != CrushBehaviour::Respawn && rules.intersects_player(&entry.group, target) {
+        blocked_evw.send(ScaleBlockedEvent(entry.group.clone()));
+        from_stack.push(entry);
+        return;
+    }
+
+    state.set(target);
+    history.commit(&entry.group, state.scale());
+    to_stack.push(entry);
 }
 
 // UI
 // TODO: Controls ( Change: Scroll wheel (30px/step))
 const UNSELECTED_BG: Color = Color::rgb(0.75, 0.75, 0.75);
 const SELECTED_BG: Color = Color::rgb(0.65, 0.65, 0.65);
+const BLOCKED_BG: Color = Color::rgb(0.9, 0.45, 0.45);
+const LOCKED_BG: Color = Color::rgb(0.45, 0.45, 0.45);
+const BLOCKED_FLASH_SECONDS: f32 = 0.4;
+
+/// Groups whose HUD entry is flashing because a scale change was blocked
+#[derive(Resource, Default)]
+pub struct BlockedFlashes(HashMap<ScaleGroup, Timer>);
+
+#[derive(Component)]
+pub struct ScaleGroupHud;
 
 #[derive(Component)]
 pub struct ScaleGroupContainer;
 
 fn setup_ui(mut cmd: Commands) {
     cmd
-        .spawn(NodeBundle {
+        .spawn((NodeBundle {
             style: Style {
                 width: Val::Percent(100.),
                 height: Val::Px(128.),
@@ -202,9 +911,26 @@ fn setup_ui(mut cmd: Commands) {
                 ..Default::default()
             },
             ..Default::default()
-        })
+        }, ScaleGroupHud));
+}
+
+fn rebuild_ui(
+    mut cmd: Commands,
+    hud_query: Query<Entity, With<ScaleGroupHud>>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+    changed_groups: Query<(), Changed<ScaleGroupInfo>>,
+    mut removed_groups: RemovedComponents<ScaleGroupInfo>,
+) {
+    let removed = removed_groups.read().count() > 0;
+    if changed_groups.is_empty() && !removed {
+        return;
+    }
+    let Ok(hud) = hud_query.get_single() else { return };
+
+    cmd.entity(hud)
+        .despawn_descendants()
         .with_children(|parent| {
-            for group in ScaleGroup::iter() {
+            for (group, info) in ordered_groups(groups.iter()) {
                 parent.spawn((
                     NodeBundle {
                         style: Style {
@@ -214,42 +940,73 @@ fn setup_ui(mut cmd: Commands) {
                         background_color: UNSELECTED_BG.into(),
                         ..Default::default()
                     }, 
-                    group, ScaleGroupContainer))
+                    group.clone(), ScaleGroupContainer))
                     .with_children(|parent| {
                         parent.spawn((
-                            TextBundle::from_section(format!("{group:?}: "), TextStyle {
+                            TextBundle::from_section(format!("{}: ", info.name), TextStyle {
                                 font_size: 16.,
-                                color: group.into(),
+                                color: info.color,
                                 ..Default::default()
                             }),
                             Label));
                         parent.spawn((TextBundle::from_section("", TextStyle {
                                 font_size: 16.,
-                                color: group.into(),
+                                color: info.color,
                                 ..Default::default()
                         }),
                         Label,
-                        group));
+                        group.clone()));
+
+                        let mut range = format!(" ({:.2}..{:.2}", info.bounds.start(), info.bounds.end());
+                        if info.step > 0. {
+                            range += &format!(", step {:.2}", info.step);
+                        }
+                        range += ")";
+
+                        parent.spawn((TextBundle::from_section(range, TextStyle {
+                                font_size: 12.,
+                                color: info.color,
+                                ..Default::default()
+                        }),
+                        Label));
                     });
             }
         });
 }
 
 fn update_ui_factors(mut q: Query<(&mut Text, &ScaleGroup)>, factors: Query<(&ScaleGroup, &Scale)>) {
-    let scales = factors.iter().map(|(g, s)| (*g, s)).collect::<HashMap<ScaleGroup, &Scale>>();
+    let scales = factors.iter().collect::<HashMap<&ScaleGroup, &Scale>>();
 
     for (mut text, group) in q.iter_mut() {
-        let factor = scales.get(group).unwrap().0;
-        text.sections[0].value = format!("{:.2}", factor + 1.);
+        let Some(factor) = scales.get(group) else { continue };
+        text.sections[0].value = format!("{:.2}", factor.0);
+    }
+}
+
+fn update_blocked_flashes(
+    time: Res<Time>,
+    mut flashes: ResMut<BlockedFlashes>,
+    mut blocked_evr: EventReader<ScaleBlockedEvent>,
+) {
+    flashes.0.retain(|_, timer| !timer.tick(time.delta()).finished());
+
+    for ev in blocked_evr.read() {
+        flashes.0.insert(ev.0.clone(), Timer::from_seconds(BLOCKED_FLASH_SECONDS, TimerMode::Once));
     }
 }
 
 fn update_ui_selected(
     mut q: Query<(&mut BackgroundColor, &ScaleGroup), With<ScaleGroupContainer>>,
     selected_group: Res<SelectedGroup>,
+    flashes: Res<BlockedFlashes>,
+    locked: Query<&ScaleGroup, With<Locked>>,
 ) {
     for (mut bg, group) in q.iter_mut() {
-        if *group == selected_group.0 {
+        if flashes.0.contains_key(group) {
+            bg.0 = BLOCKED_BG;
+        } else if locked.iter().any(|other| other == group) {
+            bg.0 = LOCKED_BG;
+        } else if Some(group) == selected_group.0.as_ref() {
             bg.0 = SELECTED_BG;
         } else {
             bg.0 = UNSELECTED_BG;
@@ -259,6 +1016,83 @@ fn update_ui_selected(
 
 
 // YOLECK
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct YoleckScaleGroup {
+    id: ScaleGroup,
+    name: String,
+    color: Color,
+    order: i32,
+    start: Scalar,
+    min: Scalar,
+    max: Scalar,
+    #[serde(default)]
+    step: Scalar,
+}
+
+impl Default for YoleckScaleGroup {
+    fn default() -> Self {
+        Self {
+            id: ScaleGroup(String::from("Red")),
+            name: String::from("Red"),
+            color: Color::RED,
+            order: 0,
+            start: 1.,
+            min: 0.1,
+            max: 5.,
+            step: 0.,
+        }
+    }
+}
+
+fn populate_scale_group(mut pop: YoleckPopulate<&YoleckScaleGroup>) {
+    pop.populate(|_ctx, mut cmd, group| {
+        cmd.insert(ScaleGroupBundle::new(
+            group.id.clone(),
+            ScaleGroupInfo {
+                name: group.name.clone(),
+                color: group.color,
+                order: group.order,
+                start: group.start,
+                // Min can be dragged past max in the editor, and clamping would panic on a reversed range
+                bounds: group.min.min(group.max)..=group.min.max(group.max),
+                step: group.step,
+            },
+        ));
+    })
+}
+
+fn edit_scale_group(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut YoleckScaleGroup>) {
+    if let Ok(mut group) = edit.get_single_mut() {
+        ui.horizontal(|ui| {
+            ui.label("ID: ");
+            ui.text_edit_singleline(&mut group.id.0);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Name: ");
+            ui.text_edit_singleline(&mut group.name);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Colour: ");
+            let mut color = group.color.as_rgba_f32();
+            if ui.color_edit_button_rgba_unmultiplied(&mut color).changed() {
+                group.color = Color::rgba(color[0], color[1], color[2], color[3]);
+            }
+        });
+
+        ui.add(egui::DragValue::new(&mut group.order).prefix("Order: "));
+
+        ui.horizontal(|ui| {
+            ui.add(egui::Label::new("Scale: "));
+            ui.add(egui::DragValue::new(&mut group.start).speed(0.01).fixed_decimals(2).prefix("Start: "));
+            ui.add(egui::DragValue::new(&mut group.min).speed(0.05).fixed_decimals(2).prefix("Min: "));
+            ui.add(egui::DragValue::new(&mut group.max).speed(0.05).fixed_decimals(2).prefix("Max: "));
+            ui.add(egui::DragValue::new(&mut group.step).speed(0.01).fixed_decimals(2).clamp_range(0.0..=10.).prefix("Step: "));
+        });
+    }
+}
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
 pub struct YoleckScalable {
     width: Scalar,
@@ -270,6 +1104,10 @@ pub struct YoleckScalable {
     min: Scalar,
     max: Scalar,
     factor: Scalar,
+    #[serde(default)]
+    factor_y: Option<Scalar>,
+    #[serde(default)]
+    curve: ScaleCurve,
 }
 
 impl Default for YoleckScalable {
@@ -280,64 +1118,151 @@ impl Default for YoleckScalable {
             x: 0.,
             y: 0.,
             direction: ScaleDirection::Up,
-            group: ScaleGroup::Red,
+            group: ScaleGroup(String::from("Red")),
             min: 0.5,
             max: 1.5,
             factor: 1.,
+            factor_y: None,
+            curve: ScaleCurve::Linear,
         }
     }
 }
 
-fn populate_scalable(mut pop: YoleckPopulate<&YoleckScalable>) {
-    pop.populate(|_ctx, mut cmd, scalable| {
-        cmd.insert(ScalableBundle::new(
+impl YoleckScalable {
+    pub fn position(&self) -> Vec2 {
+        Vec2::new(self.x, self.y)
+    }
+
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.width, self.height)
+    }
+
+    pub fn bundle(&self) -> ScalableBundle {
+        let factor_y = if self.direction.is_two_axis() {
+            self.factor_y.unwrap_or(self.factor)
+        } else {
+            self.factor
+        };
+
+        ScalableBundle::new(
             Scalable {
-                factor: scalable.factor,
-                direction: scalable.direction,
-                position: Vec2::new(scalable.x, scalable.y),
-                bounds: scalable.min..=scalable.max,
-                size: Vec2::new(scalable.width, scalable.height),
+                factor: Vec2::new(self.factor, factor_y),
+                curve: self.curve.clone().sorted(),
+                direction: self.direction,
+                position: self.position(),
+                bounds: self.min.min(self.max)..=self.min.max(self.max),
+                size: self.size(),
             },
-            Vec2::new(scalable.width, scalable.height),
-            scalable.group,
-        ));
-    })
-}
+            self.size(),
+            self.group.clone(),
+        )
+    }
 
-fn edit_scalable(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut YoleckScalable>) {
-    if let Ok(mut scalable) = edit.get_single_mut() {
+    /// Editor controls for the size and position
+    pub fn edit_shape(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::Slider::new(&mut self.width, 50.0..=2000.0).prefix("Width: "));
+        ui.add(egui::Slider::new(&mut self.height, 50.0..=1000.0).prefix("Height: "));
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.x).speed(1.).fixed_decimals(0).prefix("X: "));
+
+            // TODO: clamp range to height of scale factor UI - INFINITY
+            ui.add(egui::DragValue::new(&mut self.y).speed(1.).fixed_decimals(0).prefix("Y: "));
+        });
+    }
+
+    /// Editor controls for the group, direction, factors and curve
+    pub fn edit_scaling(&mut self, ui: &mut egui::Ui, groups: &Query<(&ScaleGroup, &ScaleGroupInfo)>) {
         egui::ComboBox::from_label("Group")
-            .selected_text(format!("{:?}", scalable.group))
-            .show_ui(&mut ui, |ui| {
-                for variant in ScaleGroup::iter() {
-                    ui.selectable_value(&mut scalable.group, variant, format!("{variant:?}"));
+            .selected_text(self.group.0.as_str())
+            .show_ui(ui, |ui| {
+                for (group, info) in ordered_groups(groups.iter()) {
+                    ui.selectable_value(&mut self.group, group.clone(), info.name.as_str());
                 }
             });
 
         egui::ComboBox::from_label("Direction")
-            .selected_text(format!("{:?}", scalable.direction))
-            .show_ui(&mut ui, |ui| {
+            .selected_text(format!("{:?}", self.direction))
+            .show_ui(ui, |ui| {
                 for variant in ScaleDirection::iter() {
-                    ui.selectable_value(&mut scalable.direction, variant, format!("{variant:?}"));
+                    ui.selectable_value(&mut self.direction, variant, format!("{variant:?}"));
                 }
             });
 
-        ui.add(egui::Slider::new(&mut scalable.width, 50.0..=2000.0).prefix("Width: "));
-        ui.add(egui::Slider::new(&mut scalable.height, 50.0..=1000.0).prefix("Height: "));
-
-        ui.horizontal(|ui| {
-            ui.add(egui::DragValue::new(&mut scalable.x).speed(1.).fixed_decimals(0).prefix("X: "));
-
-            // TODO: clamp range to height of scale factor UI - INFINITY
-            ui.add(egui::DragValue::new(&mut scalable.y).speed(1.).fixed_decimals(0).prefix("Y: "));
-        });
-
         ui.horizontal(|ui| {
             ui.add(egui::Label::new("Scale: "));
-            ui.add(egui::DragValue::new(&mut scalable.factor).speed(0.01).fixed_decimals(2).clamp_range(-10.0..=10.).prefix("Factor: "));
-            ui.add(egui::DragValue::new(&mut scalable.min).speed(0.05).fixed_decimals(2).prefix("Min: "));
-            ui.add(egui::DragValue::new(&mut scalable.max).speed(0.05).fixed_decimals(2).prefix("Max: "));
+            ui.add(egui::DragValue::new(&mut self.factor).speed(0.01).fixed_decimals(2).clamp_range(-10.0..=10.).prefix("Factor: "));
+            ui.add(egui::DragValue::new(&mut self.min).speed(0.05).fixed_decimals(2).prefix("Min: "));
+            ui.add(egui::DragValue::new(&mut self.max).speed(0.05).fixed_decimals(2).prefix("Max: "));
         });
+
+        if self.direction.is_two_axis() {
+            ui.horizontal(|ui| {
+                let mut separate = self.factor_y.is_some();
+                if ui.checkbox(&mut separate, "Separate Y factor").changed() {
+                    self.factor_y = separate.then_some(self.factor);
+                }
+
+                if let Some(factor_y) = self.factor_y.as_mut() {
+                    ui.add(egui::DragValue::new(factor_y).speed(0.01).fixed_decimals(2).clamp_range(-10.0..=10.).prefix("Y Factor: "));
+                }
+            });
+        }
+
+        egui::ComboBox::from_label("Curve")
+            .selected_text(<&str>::from(&self.curve))
+            .show_ui(ui, |ui| {
+                for variant in ScaleCurve::iter() {
+                    let selected = std::mem::discriminant(&self.curve) == std::mem::discriminant(&variant);
+                    if ui.selectable_label(selected, <&str>::from(&variant)).clicked() && !selected {
+                        // Keep the points when switching between the two point-based curves
+                        let curve = match (&self.curve, variant) {
+                            (ScaleCurve::Stepped(points) | ScaleCurve::Table(points), ScaleCurve::Stepped(_)) => ScaleCurve::Stepped(points.clone()),
+                            (ScaleCurve::Stepped(points) | ScaleCurve::Table(points), ScaleCurve::Table(_)) => ScaleCurve::Table(points.clone()),
+                            (_, variant) => variant,
+                        };
+                        self.curve = curve;
+                    }
+                }
+            });
+
+        if let ScaleCurve::Stepped(points) | ScaleCurve::Table(points) = &mut self.curve {
+            let mut removed = None;
+            for (i, point) in points.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut point.x).speed(0.05).fixed_decimals(2).prefix("At: "));
+                    ui.add(egui::DragValue::new(&mut point.y).speed(0.05).fixed_decimals(2).prefix("Value: "));
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+            }
+
+            if let Some(i) = removed {
+                points.remove(i);
+            }
+
+            if ui.button("Add point").clicked() {
+                points.push(points.last().copied().unwrap_or(Vec2::ONE));
+            }
+        }
+    }
+}
+
+fn populate_scalable(mut pop: YoleckPopulate<&YoleckScalable>) {
+    pop.populate(|_ctx, mut cmd, scalable| {
+        cmd.insert(scalable.bundle());
+    })
+}
+
+fn edit_scalable(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut YoleckScalable>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+) {
+    if let Ok(mut scalable) = edit.get_single_mut() {
+        scalable.edit_scaling(&mut ui, &groups);
+        scalable.edit_shape(&mut ui);
     }
 }
 
@@ -349,17 +1274,76 @@ impl bevy::app::Plugin for Plugin {
         app
             .add_event::<SelectFactorEvent>()
             .add_event::<ChangeScaleEvent>()
-            .add_systems(Startup, (setup_groups,setup_ui))
+            .add_event::<SetScaleEvent>()
+            .add_event::<ScaleBlockedEvent>()
+            .init_resource::<BlockedFlashes>()
+            .init_resource::<SelectedGroup>()
+            .init_resource::<ScaleHistory>()
+            .init_resource::<ScaleTweening>()
+            .add_systems(Startup, setup_ui)
             .add_systems(Update, (
-                (handle_keyboard_input, handle_mouse_scrolling).in_set(GameplaySet::Input),
-                (update_selection, update_scale).chain().in_set(GameplaySet::Update),
-                apply_scale_factors.in_set(GameplaySet::Movement),
-                update_ui_factors, update_ui_selected,
+                (handle_keyboard_input, handle_keyboard_scaling, handle_analog_scaling, handle_mouse_scrolling).in_set(GameplaySet::Input),
+                (update_selection, handle_history_input, update_scale).chain().in_set(GameplaySet::Update),
+                update_group_colors, rebuild_ui, update_ui_factors,
+                update_fallback_groups, check_unknown_groups,
+                (update_blocked_flashes, update_ui_selected).chain(),
             ))
+            .add_systems(PhysicsSchedule, apply_scale_factors.before(PhysicsStepSet::BroadPhase).run_if(in_state(GameState::InGame)))
+            .add_systems(PostUpdate, (settle_scales, record_history).chain().after(check_squished).run_if(in_state(GameState::InGame).and_then(in_state(PauseState::Running))))
             .add_systems(OnExit(GameState::InGame), reset_scales);
 
+        app.add_yoleck_entity_type(YoleckEntityType::new("ScaleGroup").with::<YoleckScaleGroup>());
+        app.add_yoleck_edit_system(edit_scale_group);
+        app.yoleck_populate_schedule_mut().add_systems(populate_scale_group);
+
         app.add_yoleck_entity_type(YoleckEntityType::new("Scalable").with::<YoleckScalable>());
         app.add_yoleck_edit_system(edit_scalable);
         app.yoleck_populate_schedule_mut().add_systems(populate_scalable);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A level with a single selected group, stepping by halves from 0.5 to 3
+    fn scaling_app() -> (App, Entity) {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .add_event::<ChangeScaleEvent>()
+            .add_event::<SetScaleEvent>()
+            .add_event::<ScaleBlockedEvent>()
+            .init_resource::<LevelRun>()
+            .init_resource::<SpatialQueryPipeline>()
+            .insert_resource(SelectedGroup(Some(ScaleGroup(String::from("Red")))))
+            .add_systems(Update, update_scale);
+
+        let info = ScaleGroupInfo {
+            name: String::from("Red"),
+            color: Color::RED,
+            order: 0,
+            start: 1.,
+            bounds: 0.5..=3.,
+            step: 0.5,
+        };
+        let group = app.world.spawn(ScaleGroupBundle::new(ScaleGroup(String::from("Red")), info)).id();
+
+        (app, group)
+    }
+
+    #[test]
+    fn exact_scale_ignores_partial_steps() {
+        let (mut app, group) = scaling_app();
+
+        // Not enough to reach the next step, but it leaves the unsnapped scale between steps
+        app.world.send_event(ChangeScaleEvent(0.2));
+        app.update();
+        assert_eq!(app.world.get::<Scale>(group).unwrap().0, 1.);
+
+        app.world.send_event(SetScaleEvent(1.5));
+        app.update();
+        assert_eq!(app.world.get::<Scale>(group).unwrap().0, 1.5);
+        assert_eq!(app.world.get::<UnsnappedScale>(group).unwrap().0, 1.5);
+    }
+}
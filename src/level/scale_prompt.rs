@@ -0,0 +1,163 @@
+//! A prompt for typing an exact scale for the selected group
+use bevy::{prelude::*, window::ReceivedCharacter};
+use bevy_xpbd_2d::math::*;
+
+use crate::{GameplaySet, PauseState, input::{Action, Actions}};
+
+use super::resizable::{ScaleGroup, ScaleGroupInfo, SelectedGroup, SetScaleEvent};
+
+const PROMPT_BG: Color = Color::rgba(0.1, 0.1, 0.1, 0.85);
+
+// RESOURCES
+/// What has been typed into the prompt so far
+#[derive(Resource, Default)]
+pub struct ScalePrompt {
+    text: String,
+    /// Why the typed text was refused, shown until it is edited
+    error: Option<String>,
+}
+
+// COMPONENTS
+#[derive(Component)]
+pub struct ScalePromptRoot;
+
+#[derive(Component)]
+pub struct ScalePromptText;
+
+// SYSTEMS
+fn open_prompt(
+    actions: Actions,
+    selected: Res<SelectedGroup>,
+    mut prompt: ResMut<ScalePrompt>,
+    mut pause: ResMut<NextState<PauseState>>,
+) {
+    if actions.just_pressed(Action::SetScale) && selected.get().is_some() {
+        *prompt = ScalePrompt::default();
+        pause.set(PauseState::EnteringScale);
+    }
+}
+
+/// Also throws away anything typed before the prompt opened
+fn setup_prompt(mut cmd: Commands, mut chars: ResMut<Events<ReceivedCharacter>>) {
+    chars.clear();
+
+    cmd
+        .spawn((NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(144.),
+                width: Val::Percent(100.),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            ..Default::default()
+        }, ScalePromptRoot))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(8.)),
+                        ..Default::default()
+                    },
+                    background_color: PROMPT_BG.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((TextBundle::from_section("", TextStyle {
+                        font_size: 24.,
+                        color: Color::ANTIQUE_WHITE,
+                        ..Default::default()
+                    }), ScalePromptText));
+                });
+        });
+}
+
+fn despawn_prompt(mut cmd: Commands, q: Query<Entity, With<ScalePromptRoot>>) {
+    for entity in q.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn type_prompt_text(mut chars: EventReader<ReceivedCharacter>, keys: Res<Input<KeyCode>>, mut prompt: ResMut<ScalePrompt>) {
+    for ev in chars.read() {
+        // Either separator reads as a decimal point, and a sign only makes sense first
+        let char = if ev.char == ',' { '.' } else { ev.char };
+        if char.is_ascii_digit() || char == '.' || (char == '-' && prompt.text.is_empty()) {
+            prompt.text.push(char);
+            prompt.error = None;
+        }
+    }
+
+    // Editing text isn't an action, so it stays on the keyboard
+    if keys.just_pressed(KeyCode::Back) {
+        prompt.text.pop();
+        prompt.error = None;
+    }
+}
+
+/// Confirm sets the typed scale, Cancel closes the prompt
+fn handle_prompt_input(
+    actions: Actions,
+    selected: Res<SelectedGroup>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+    mut prompt: ResMut<ScalePrompt>,
+    mut pause: ResMut<NextState<PauseState>>,
+    mut set_scale_evw: EventWriter<SetScaleEvent>,
+) {
+    if actions.just_pressed(Action::Cancel) {
+        pause.set(PauseState::Running);
+    } else if actions.just_pressed(Action::Confirm) {
+        let Ok(value) = prompt.text.parse::<Scalar>() else {
+            prompt.error = Some(String::from("not a number"));
+            return;
+        };
+
+        if let Some((_, info)) = groups.iter().find(|(group, _)| Some(*group) == selected.get()) {
+            let (min, max) = (*info.bounds().start(), *info.bounds().end());
+            if !(min..=max).contains(&value) {
+                prompt.error = Some(format!("must be from {min} to {max}"));
+                return;
+            }
+        }
+
+        set_scale_evw.send(SetScaleEvent(value));
+        pause.set(PauseState::Running);
+    }
+}
+
+fn update_prompt_text(
+    prompt: Res<ScalePrompt>,
+    selected: Res<SelectedGroup>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+    mut texts: Query<&mut Text, With<ScalePromptText>>,
+) {
+    let Some((_, info)) = groups.iter().find(|(group, _)| Some(*group) == selected.get()) else { return };
+
+    let mut label = format!("Set {} to ({}-{}): {}_", info.name(), info.bounds().start(), info.bounds().end(), prompt.text);
+    if let Some(error) = &prompt.error {
+        label.push_str("  ");
+        label.push_str(error);
+    }
+
+    for mut text in texts.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<ScalePrompt>()
+            .add_systems(OnEnter(PauseState::EnteringScale), setup_prompt)
+            .add_systems(OnExit(PauseState::EnteringScale), despawn_prompt)
+            .add_systems(Update, open_prompt.in_set(GameplaySet::Input))
+            .add_systems(Update, (
+                type_prompt_text,
+                handle_prompt_input,
+                update_prompt_text.run_if(resource_changed::<ScalePrompt>()),
+            ).chain().run_if(in_state(PauseState::EnteringScale)));
+    }
+}
@@ -0,0 +1,153 @@
+//! Level bounds, which stop the camera scrolling and kill players who fall out of them
+use bevy::{prelude::*, ecs::system::SystemParam, window::PrimaryWindow};
+use bevy_yoleck::{prelude::*, bevy_egui::EguiContexts};
+use serde::{Deserialize, Serialize};
+
+use crate::{EditorSet, camera::{WINDOW_BOTTOM_LEFT, WINDOW_SIZE, WINDOW_TOP_RIGHT}};
+
+/// How close the cursor must be to a corner to drag it
+const HANDLE_RADIUS: f32 = 12.;
+
+// COMPONENTS
+#[derive(Component, Clone, Copy)]
+pub struct LevelBounds(pub Rect);
+
+/// Levels without bounds keep the camera above and to the right of the origin, and never stop it otherwise
+impl Default for LevelBounds {
+    fn default() -> Self {
+        Self(Rect { min: WINDOW_BOTTOM_LEFT, max: Vec2::INFINITY })
+    }
+}
+
+impl LevelBounds {
+    /// The height below which the player dies
+    pub fn kill_plane(&self) -> f32 {
+        self.0.min.y
+    }
+
+    /// Keeps a window-sized view centred on `position` inside the bounds, grown by `margin` on every side
+    pub fn clamp_camera(&self, position: Vec2, margin: f32) -> Vec2 {
+        let clamp_axis = |value: f32, min: f32, max: f32, half_size: f32| {
+            let (min, max) = (min - margin + half_size, max + margin - half_size);
+            if min >= max { (min + max) / 2. } else { value.clamp(min, max) }
+        };
+
+        Vec2::new(
+            clamp_axis(position.x, self.0.min.x, self.0.max.x, WINDOW_SIZE.x / 2.),
+            clamp_axis(position.y, self.0.min.y, self.0.max.y, WINDOW_SIZE.y / 2.),
+        )
+    }
+}
+
+// SYSTEM PARAMS
+/// Where the mouse is in the world, as seen by the camera
+#[derive(SystemParam)]
+struct WorldCursor<'w, 's> {
+    window_query: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+}
+
+impl WorldCursor<'_, '_> {
+    fn position(&self) -> Option<Vec2> {
+        let window = self.window_query.get_single().ok()?;
+        let (camera, camera_transform) = self.camera_query.get_single().ok()?;
+        window.cursor_position().and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor))
+    }
+}
+
+// SYSTEMS
+fn draw_bounds(mut gizmos: Gizmos, bounds_query: Query<&LevelBounds>) {
+    for bounds in bounds_query.iter() {
+        gizmos.rect_2d(bounds.0.center(), 0., bounds.0.size(), Color::ORANGE_RED);
+    }
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct YoleckLevelBounds {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Default for YoleckLevelBounds {
+    fn default() -> Self {
+        Self {
+            min: WINDOW_BOTTOM_LEFT,
+            max: WINDOW_TOP_RIGHT,
+        }
+    }
+}
+
+fn populate_bounds(mut pop: YoleckPopulate<&YoleckLevelBounds>) {
+    pop.populate(|_ctx, mut cmd, bounds| {
+        cmd.insert(LevelBounds(Rect::from_corners(bounds.min, bounds.max)));
+    })
+}
+
+fn edit_bounds(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut YoleckLevelBounds>,
+    mut gizmos: Gizmos,
+    mut egui: EguiContexts,
+    mouse_input: Res<Input<MouseButton>>,
+    cursor: WorldCursor,
+    mut dragged_corner: Local<Option<usize>>,
+) {
+    let Ok(mut bounds) = edit.get_single_mut() else {
+        *dragged_corner = None;
+        return
+    };
+
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut bounds.min.x).speed(1.).fixed_decimals(0).prefix("Left: "));
+        ui.add(egui::DragValue::new(&mut bounds.max.x).speed(1.).fixed_decimals(0).prefix("Right: "));
+    });
+
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut bounds.min.y).speed(1.).fixed_decimals(0).prefix("Bottom: "));
+        ui.add(egui::DragValue::new(&mut bounds.max.y).speed(1.).fixed_decimals(0).prefix("Top: "));
+    });
+
+    // Drag handles on each corner
+    let corners = [
+        bounds.min,
+        Vec2::new(bounds.max.x, bounds.min.y),
+        bounds.max,
+        Vec2::new(bounds.min.x, bounds.max.y),
+    ];
+    for corner in corners {
+        gizmos.circle_2d(corner, HANDLE_RADIUS, Color::ORANGE_RED);
+    }
+
+    if !mouse_input.pressed(MouseButton::Left) {
+        *dragged_corner = None;
+        return;
+    }
+
+    let Some(cursor) = cursor.position() else { return };
+    let cursor = cursor.round();
+
+    if mouse_input.just_pressed(MouseButton::Left) && !egui.ctx_mut().wants_pointer_input() {
+        *dragged_corner = corners.iter().position(|corner| corner.distance(cursor) <= HANDLE_RADIUS);
+    }
+
+    match *dragged_corner {
+        Some(0) => bounds.min = cursor,
+        Some(1) => (bounds.max.x, bounds.min.y) = (cursor.x, cursor.y),
+        Some(2) => bounds.max = cursor,
+        Some(3) => (bounds.min.x, bounds.max.y) = (cursor.x, cursor.y),
+        _ => {},
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, draw_bounds.in_set(EditorSet));
+        app.add_yoleck_entity_type(YoleckEntityType::new("LevelBounds").with::<YoleckLevelBounds>());
+        app.add_yoleck_edit_system(edit_bounds);
+        app.yoleck_populate_schedule_mut().add_systems(populate_bounds);
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+use bevy_yoleck::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator};
+
+// COMPONENTS
+/// What happens when a scalable object squishes the player
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default, EnumIter)]
+pub enum CrushBehaviour {
+    #[default]
+    Respawn,
+    /// Undo the scale change after the player was squished
+    Refuse,
+    /// Stop scaling before objects reach the player
+    Solid,
+}
+
+/// Level-wide rules, read from the level's single `LevelSettings` entity
+#[derive(Component, Clone, Default)]
+pub struct LevelSettings {
+    pub crush: CrushBehaviour,
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent, Default)]
+pub struct YoleckLevelSettings {
+    #[serde(default)]
+    crush: CrushBehaviour,
+}
+
+fn populate_settings(mut pop: YoleckPopulate<&YoleckLevelSettings>) {
+    pop.populate(|_ctx, mut cmd, settings| {
+        cmd.insert(LevelSettings {
+            crush: settings.crush,
+        });
+    })
+}
+
+fn edit_settings(mut ui: ResMut<YoleckUi>, mut edit: YoleckEdit<&mut YoleckLevelSettings>) {
+    if let Ok(mut settings) = edit.get_single_mut() {
+        egui::ComboBox::from_label("When squished")
+            .selected_text(format!("{:?}", settings.crush))
+            .show_ui(&mut ui, |ui| {
+                for variant in CrushBehaviour::iter() {
+                    ui.selectable_value(&mut settings.crush, variant, format!("{variant:?}"));
+                }
+            });
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_yoleck_entity_type(YoleckEntityType::new("LevelSettings").with::<YoleckLevelSettings>());
+        app.add_yoleck_edit_system(edit_settings);
+        app.yoleck_populate_schedule_mut().add_systems(populate_settings);
+    }
+}
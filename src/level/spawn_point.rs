@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_xpbd_2d::{prelude::*, math::Scalar};
+use bevy_yoleck::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{GameplaySet, player::{Player, DEFAULT_SPAWN}};
+
+use super::LevelLoaded;
+
+// COMPONENTS
+/// Where the player enters the level and returns to when they die
+#[derive(Component)]
+pub struct SpawnPoint(pub Vec2);
+
+// SYSTEMS
+/// The player is spawned before the level is populated, so it is moved onto the spawn point once that appears
+fn place_player_at_spawn_point(
+    spawn_points: Query<&SpawnPoint, Added<SpawnPoint>>,
+    mut player_query: Query<(&mut Transform, &mut LinearVelocity), With<Player>>,
+) {
+    let Some(spawn_point) = spawn_points.iter().next() else { return };
+
+    for (mut transform, mut velocity) in player_query.iter_mut() {
+        transform.translation.x = spawn_point.0.x;
+        transform.translation.y = spawn_point.0.y;
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+fn check_spawn_point_count(
+    mut loaded_evr: EventReader<LevelLoaded>,
+    spawn_points: Query<(), With<YoleckSpawnPoint>>,
+) {
+    if loaded_evr.read().count() == 0 {
+        return;
+    }
+
+    match spawn_points.iter().count() {
+        0 => bevy::log::warn!("Level has no spawn point, the player will start at {DEFAULT_SPAWN}"),
+        1 => {},
+        count => bevy::log::warn!("Level has {count} spawn points, only one of them will be used"),
+    }
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct YoleckSpawnPoint {
+    x: Scalar,
+    y: Scalar,
+}
+
+impl Default for YoleckSpawnPoint {
+    fn default() -> Self {
+        Self {
+            x: DEFAULT_SPAWN.x,
+            y: DEFAULT_SPAWN.y,
+        }
+    }
+}
+
+fn populate_spawn_point(mut pop: YoleckPopulate<&YoleckSpawnPoint>, asset_server: Res<AssetServer>) {
+    pop.populate(|ctx, mut cmd, spawn_point| {
+        let position = Vec2::new(spawn_point.x, spawn_point.y);
+        cmd.insert(SpawnPoint(position));
+
+        if ctx.is_in_editor() {
+            cmd.insert(SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(25., 45.)),
+                    color: Color::rgba(1., 1., 1., 0.5),
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(5.)),
+                texture: asset_server.load("sprites/player.png"),
+                ..Default::default()
+            });
+        }
+    })
+}
+
+fn edit_spawn_point(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut YoleckSpawnPoint>,
+    spawn_points: Query<(), With<YoleckSpawnPoint>>,
+) {
+    if let Ok(mut spawn_point) = edit.get_single_mut() {
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut spawn_point.x).speed(1.).fixed_decimals(0).prefix("X: "));
+            ui.add(egui::DragValue::new(&mut spawn_point.y).speed(1.).fixed_decimals(0).prefix("Y: "));
+        });
+
+        if spawn_points.iter().count() > 1 {
+            ui.colored_label(egui::Color32::YELLOW, "This level has several spawn points, only one will be used");
+        }
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (place_player_at_spawn_point.in_set(GameplaySet::Update), check_spawn_point_count));
+        app.add_yoleck_entity_type(YoleckEntityType::new("SpawnPoint").with::<YoleckSpawnPoint>());
+        app.add_yoleck_edit_system(edit_spawn_point);
+        app.yoleck_populate_schedule_mut().add_systems(populate_spawn_point);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_starts_at_a_spawn_point_populated_after_it() {
+        let mut app = App::new();
+        app.add_systems(Update, place_player_at_spawn_point);
+
+        let player = app.world.spawn((
+            Player,
+            Transform::from_translation(DEFAULT_SPAWN.extend(10.)),
+            LinearVelocity(Vec2::new(50., -20.)),
+        )).id();
+        app.update();
+
+        let spawn_point = Vec2::new(DEFAULT_SPAWN.x + 300., DEFAULT_SPAWN.y + 150.);
+        app.world.spawn(SpawnPoint(spawn_point));
+        app.update();
+
+        let transform = app.world.get::<Transform>(player).unwrap();
+        assert_eq!(transform.translation, spawn_point.extend(10.));
+        assert_eq!(app.world.get::<LinearVelocity>(player).unwrap().0, Vec2::ZERO);
+    }
+}
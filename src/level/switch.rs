@@ -0,0 +1,511 @@
+//! Pressure plates, levers and timed buttons, which change scale groups and open doors while active
+use bevy::{prelude::*, utils::{HashMap, HashSet}};
+use bevy_xpbd_2d::{prelude::*, math::*};
+use bevy_yoleck::prelude::*;
+use serde::{Deserialize, Serialize};
+use strum::{EnumIter, IntoEnumIterator, IntoStaticStr};
+
+use crate::{EditorSet, GameplaySet, GameState, input::{Action, Actions}, player::{Player, respawn::Respawn}};
+
+use super::{LevelLoaded, LevelRun, checkpoint::{ActiveCheckpoint, restore_checkpoint_scales}, hazard::Hazard, lock::{Door, OpenedDoors}, resizable::{Locked, Scalable, ScaleGroup, ScaleGroupInfo, ScaleGroupState, ScaleHistory, ScalingRules, ordered_groups, update_fallback_groups}, settings::CrushBehaviour};
+
+const INACTIVE_COLOR: Color = Color::GRAY;
+const ACTIVE_COLOR: Color = Color::LIME_GREEN;
+const LINK_COLOR: Color = Color::CYAN;
+const PLATE_HEIGHT: Scalar = 6.;
+
+/// What can hold a pressure plate down: the player, and scalable objects other than hazards
+type PlatePusher = Or<(With<Player>, (With<Scalable>, Without<Hazard>))>;
+
+// COMPONENTS
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug, Default, EnumIter)]
+pub enum SwitchKind {
+    /// Active while the player or a scalable object rests on it
+    #[default]
+    PressurePlate,
+    /// Toggled by interacting with it
+    Lever,
+    /// Active for a while after interacting with it
+    TimedButton,
+}
+
+impl SwitchKind {
+    fn size(self, width: Scalar) -> Vec2 {
+        match self {
+            SwitchKind::PressurePlate => Vec2::new(width, PLATE_HEIGHT),
+            SwitchKind::Lever => Vec2::new(8., 30.),
+            SwitchKind::TimedButton => Vec2::splat(16.),
+        }
+    }
+}
+
+/// What a switch does to the level, by the id of the group or door it targets
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug, EnumIter, IntoStaticStr)]
+pub enum SwitchEffect {
+    /// Moves the group to `value` while active, and back to where it was afterwards
+    SetScale { group: ScaleGroup, value: Scalar },
+    /// Stops the player changing the group while active
+    LockGroup(ScaleGroup),
+    /// Stops the player changing the group until active
+    UnlockGroup(ScaleGroup),
+    OpenDoor(String),
+}
+
+impl SwitchEffect {
+    fn group(&self) -> Option<&ScaleGroup> {
+        match self {
+            SwitchEffect::SetScale { group, .. } | SwitchEffect::LockGroup(group) | SwitchEffect::UnlockGroup(group) => Some(group),
+            SwitchEffect::OpenDoor(_) => None,
+        }
+    }
+
+    /// Describes the target if nothing in the level has its id
+    fn missing_target(&self, groups: &Query<&ScaleGroup, With<ScaleGroupInfo>>, doors: &Query<&Door>) -> Option<String> {
+        match self {
+            SwitchEffect::OpenDoor(id) => (!doors.iter().any(|door| door.id() == id.as_str()))
+                .then(|| format!("door {id:?}")),
+            _ => self.group()
+                .filter(|target| !groups.iter().any(|group| group == *target))
+                .map(|target| format!("scale group {:?}", target.0)),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Switch {
+    kind: SwitchKind,
+    /// How long a timed button stays active, in seconds
+    duration: f32,
+    effects: Vec<SwitchEffect>,
+}
+
+#[derive(Component, Default)]
+pub struct SwitchState {
+    active: bool,
+    /// Whether the effects of `active` have been applied yet
+    applied: bool,
+    /// Seconds until a timed button turns off
+    remaining: f32,
+    /// The scales of the groups this switch set, from before it set them
+    restore: HashMap<ScaleGroup, Scalar>,
+}
+
+// BUNDLE
+#[derive(Bundle)]
+pub struct SwitchBundle {
+    sprite: SpriteBundle,
+    trigger: ShapeCaster,
+    switch: Switch,
+    state: SwitchState,
+}
+
+impl SwitchBundle {
+    pub fn new(position: Vec2, width: Scalar, switch: Switch) -> Self {
+        let size = switch.kind.size(width);
+
+        // Plates only feel what is on top of them, never what they are resting on
+        let trigger = if switch.kind == SwitchKind::PressurePlate {
+            ShapeCaster::new(Collider::cuboid(size.x, 2.), Vector::new(position.x, position.y + size.y / 2. + 1.), 0.0, Vector::Y)
+                .with_max_time_of_impact(4.)
+        } else {
+            ShapeCaster::new(Collider::cuboid(size.x, size.y), Vector::new(position.x, position.y), 0.0, Vector::ONE)
+                .with_max_time_of_impact(10.)
+        };
+
+        Self {
+            sprite: SpriteBundle {
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    color: INACTIVE_COLOR,
+                    ..Default::default()
+                },
+                transform: Transform::from_translation(position.extend(0.5)),
+                ..Default::default()
+            },
+            trigger: trigger.with_max_hits(8),
+            switch,
+            state: SwitchState::default(),
+        }
+    }
+}
+
+// SYSTEMS
+fn update_switches(
+    actions: Actions,
+    time: Res<Time>,
+    mut switches: Query<(&Switch, &ShapeHits, &mut SwitchState)>,
+    player_query: Query<(), With<Player>>,
+    pusher_query: Query<(), PlatePusher>,
+) {
+    let interact = actions.just_pressed(Action::Interact);
+
+    for (switch, hits, mut state) in switches.iter_mut() {
+        let touching_player = hits.iter().any(|data| player_query.contains(data.entity));
+
+        match switch.kind {
+            SwitchKind::PressurePlate => {
+                state.active = hits.iter().any(|data| pusher_query.contains(data.entity));
+            },
+            SwitchKind::Lever => if interact && touching_player {
+                state.active = !state.active;
+            },
+            SwitchKind::TimedButton => {
+                state.remaining = if interact && touching_player {
+                    switch.duration
+                } else {
+                    (state.remaining - time.delta_seconds()).max(0.)
+                };
+                state.active = state.remaining > 0.;
+            },
+        }
+    }
+}
+
+/// Sets scales when a switch turns on, and puts them back when it turns off.
+/// Refuse and Respawn levels settle these like any other change; in Solid levels a switch can't be blocked, so it kills instead.
+fn apply_scale_effects(
+    mut switches: Query<(&Switch, &mut SwitchState)>,
+    mut groups: Query<ScaleGroupState>,
+    mut history: ResMut<ScaleHistory>,
+    rules: ScalingRules,
+    mut run: ResMut<LevelRun>,
+    mut respawn_evw: EventWriter<Respawn>,
+) {
+    let solid = rules.crush() == CrushBehaviour::Solid;
+    let mut crushed = false;
+
+    for (switch, mut state) in switches.iter_mut() {
+        if state.active == state.applied {
+            continue;
+        }
+        state.applied = state.active;
+
+        for effect in &switch.effects {
+            let SwitchEffect::SetScale { group, value } = effect else { continue };
+            let Some(mut target) = groups.iter_mut().find(|target| target.group == group) else { continue };
+
+            let value = if state.active {
+                state.restore.insert(group.clone(), target.scale());
+                *value
+            } else if let Some(scale) = state.restore.remove(group) {
+                scale
+            } else {
+                continue;
+            };
+            target.move_to(value);
+            history.commit(group, target.scale());
+
+            crushed |= solid && rules.intersects_player(group, target.scale());
+        }
+    }
+
+    if crushed {
+        run.deaths += 1;
+        respawn_evw.send(Respawn);
+    }
+}
+
+/// Respawning puts back the scales from the checkpoint, so groups held by active switches are set again afterwards.
+/// They still go back to the scales from before their switches turned on once they turn off.
+fn reapply_scale_effects(
+    mut respawn_evr: EventReader<Respawn>,
+    checkpoint: Res<ActiveCheckpoint>,
+    switches: Query<(&Switch, &SwitchState)>,
+    mut groups: Query<ScaleGroupState>,
+    mut history: ResMut<ScaleHistory>,
+) {
+    // Without a checkpoint nothing was restored
+    if respawn_evr.read().count() == 0 || checkpoint.0.is_none() {
+        return;
+    }
+
+    for (switch, state) in switches.iter() {
+        if !(state.active && state.applied) {
+            continue;
+        }
+
+        for effect in &switch.effects {
+            let SwitchEffect::SetScale { group, value } = effect else { continue };
+            let Some(mut target) = groups.iter_mut().find(|target| target.group == group) else { continue };
+
+            target.set(*value);
+            history.commit(group, target.scale());
+        }
+    }
+}
+
+/// Locks groups and opens doors for as long as their switches say to
+fn apply_lock_effects(
+    mut cmd: Commands,
+    switches: Query<(&Switch, &SwitchState)>,
+    groups: Query<(Entity, &ScaleGroup, Has<Locked>), With<ScaleGroupInfo>>,
+    mut opened: ResMut<OpenedDoors>,
+) {
+    let mut locked = HashSet::new();
+    let mut doors = HashSet::new();
+    for (switch, state) in switches.iter() {
+        for effect in &switch.effects {
+            match effect {
+                SwitchEffect::LockGroup(group) if state.active => { locked.insert(group); },
+                SwitchEffect::UnlockGroup(group) if !state.active => { locked.insert(group); },
+                SwitchEffect::OpenDoor(door) if state.active => { doors.insert(door.clone()); },
+                _ => {},
+            }
+        }
+    }
+
+    for (entity, group, is_locked) in groups.iter() {
+        match (locked.contains(group), is_locked) {
+            (true, false) => { cmd.entity(entity).insert(Locked); },
+            (false, true) => { cmd.entity(entity).remove::<Locked>(); },
+            _ => {},
+        }
+    }
+
+    if opened.0 != doors {
+        opened.0 = doors;
+    }
+}
+
+/// Leaving a level, including a playtest in the editor, turns every switch back off
+fn reset_switches(
+    mut cmd: Commands,
+    mut switches: Query<&mut SwitchState>,
+    locked: Query<Entity, With<Locked>>,
+) {
+    for mut state in switches.iter_mut() {
+        *state = SwitchState::default();
+    }
+    for entity in locked.iter() {
+        cmd.entity(entity).remove::<Locked>();
+    }
+}
+
+/// Ids are typed by hand, so a switch whose target was renamed or removed would otherwise do nothing without a word
+fn check_switch_targets(
+    mut loaded_evr: EventReader<LevelLoaded>,
+    switches: Query<&Switch>,
+    groups: Query<&ScaleGroup, With<ScaleGroupInfo>>,
+    doors: Query<&Door>,
+) {
+    if loaded_evr.read().count() == 0 {
+        return;
+    }
+
+    for effect in switches.iter().flat_map(|switch| &switch.effects) {
+        if let Some(target) = effect.missing_target(&groups, &doors) {
+            error!("A switch targets {target}, which isn't in the level");
+        }
+    }
+}
+
+fn update_switch_colors(mut switches: Query<(&SwitchState, &mut Sprite), Changed<SwitchState>>) {
+    for (state, mut sprite) in switches.iter_mut() {
+        let color = if state.active { ACTIVE_COLOR } else { INACTIVE_COLOR };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// Draws a line from each switch to everything it affects
+fn draw_switch_links(
+    mut gizmos: Gizmos,
+    switches: Query<(&Switch, &Transform)>,
+    doors: Query<(&Door, &Transform)>,
+    scalables: Query<(&ScaleGroup, &Transform), With<Scalable>>,
+) {
+    for (switch, transform) in switches.iter() {
+        let from = transform.translation.truncate();
+
+        for effect in &switch.effects {
+            let targets = match effect {
+                SwitchEffect::OpenDoor(id) => doors.iter()
+                    .filter(|(door, _)| door.id() == id.as_str())
+                    .map(|(_, transform)| transform.translation.truncate())
+                    .collect::<Vec<_>>(),
+                _ => scalables.iter()
+                    .filter(|(group, _)| Some(*group) == effect.group())
+                    .map(|(_, transform)| transform.translation.truncate())
+                    .collect(),
+            };
+
+            for to in targets {
+                gizmos.line_2d(from, to, LINK_COLOR);
+            }
+        }
+    }
+}
+
+// LEVEL EDITOR
+#[derive(Clone, PartialEq, Serialize, Deserialize, Component, YoleckComponent)]
+pub struct YoleckSwitch {
+    kind: SwitchKind,
+    x: Scalar,
+    y: Scalar,
+    /// Only used by pressure plates
+    width: Scalar,
+    /// Only used by timed buttons, in seconds
+    duration: f32,
+    effects: Vec<SwitchEffect>,
+}
+
+impl Default for YoleckSwitch {
+    fn default() -> Self {
+        Self {
+            kind: SwitchKind::PressurePlate,
+            x: 0.,
+            y: 0.,
+            width: 50.,
+            duration: 3.,
+            effects: Vec::new(),
+        }
+    }
+}
+
+fn populate_switch(mut pop: YoleckPopulate<&YoleckSwitch>) {
+    pop.populate(|_ctx, mut cmd, switch| {
+        cmd.insert(SwitchBundle::new(
+            Vec2::new(switch.x, switch.y),
+            switch.width,
+            Switch {
+                kind: switch.kind,
+                duration: switch.duration,
+                effects: switch.effects.clone(),
+            },
+        ));
+    })
+}
+
+fn edit_switch(
+    mut ui: ResMut<YoleckUi>,
+    mut edit: YoleckEdit<&mut YoleckSwitch>,
+    groups: Query<(&ScaleGroup, &ScaleGroupInfo)>,
+    group_ids: Query<&ScaleGroup, With<ScaleGroupInfo>>,
+    doors: Query<&Door>,
+) {
+    let Ok(mut switch) = edit.get_single_mut() else { return };
+    let switch = &mut *switch;
+
+    egui::ComboBox::from_label("Kind")
+        .selected_text(format!("{:?}", switch.kind))
+        .show_ui(&mut ui, |ui| {
+            for variant in SwitchKind::iter() {
+                ui.selectable_value(&mut switch.kind, variant, format!("{variant:?}"));
+            }
+        });
+
+    match switch.kind {
+        SwitchKind::PressurePlate => { ui.add(egui::Slider::new(&mut switch.width, 20.0..=500.0).prefix("Width: ")); },
+        SwitchKind::TimedButton => { ui.add(egui::DragValue::new(&mut switch.duration).speed(0.1).fixed_decimals(1).clamp_range(0.1..=60.).prefix("Seconds: ")); },
+        SwitchKind::Lever => {},
+    }
+
+    ui.horizontal(|ui| {
+        ui.add(egui::DragValue::new(&mut switch.x).speed(1.).fixed_decimals(0).prefix("X: "));
+        ui.add(egui::DragValue::new(&mut switch.y).speed(1.).fixed_decimals(0).prefix("Y: "));
+    });
+
+    let mut door_ids = doors.iter().map(|door| door.id().to_owned()).collect::<Vec<_>>();
+    door_ids.sort();
+    door_ids.dedup();
+
+    let mut removed = None;
+    for (i, effect) in switch.effects.iter_mut().enumerate() {
+        ui.push_id(i, |ui| ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("effect")
+                .selected_text(<&str>::from(&*effect))
+                .show_ui(ui, |ui| {
+                    for variant in SwitchEffect::iter() {
+                        let selected = std::mem::discriminant(&*effect) == std::mem::discriminant(&variant);
+                        if ui.selectable_label(selected, <&str>::from(&variant)).clicked() && !selected {
+                            *effect = variant;
+                        }
+                    }
+                });
+
+            match effect {
+                SwitchEffect::SetScale { group, value } => {
+                    edit_target_group(ui, group, &groups);
+                    ui.add(egui::DragValue::new(value).speed(0.05).fixed_decimals(2).prefix("Scale: "));
+                },
+                SwitchEffect::LockGroup(group) | SwitchEffect::UnlockGroup(group) => edit_target_group(ui, group, &groups),
+                SwitchEffect::OpenDoor(id) => {
+                    egui::ComboBox::from_id_source("door")
+                        .selected_text(id.as_str())
+                        .show_ui(ui, |ui| {
+                            for door in &door_ids {
+                                ui.selectable_value(id, door.clone(), door.as_str());
+                            }
+                        });
+                },
+            }
+
+            if ui.button("Remove").clicked() {
+                removed = Some(i);
+            }
+
+            if let Some(target) = effect.missing_target(&group_ids, &doors) {
+                ui.colored_label(egui::Color32::RED, format!("No {target}"));
+            }
+        }));
+    }
+
+    if let Some(i) = removed {
+        switch.effects.remove(i);
+    }
+
+    if ui.button("Add effect").clicked() {
+        switch.effects.push(SwitchEffect::OpenDoor(door_ids.first().cloned().unwrap_or_default()));
+    }
+}
+
+fn edit_target_group(ui: &mut egui::Ui, target: &mut ScaleGroup, groups: &Query<(&ScaleGroup, &ScaleGroupInfo)>) {
+    egui::ComboBox::from_id_source("group")
+        .selected_text(target.0.as_str())
+        .show_ui(ui, |ui| {
+            for (group, info) in ordered_groups(groups.iter()) {
+                ui.selectable_value(target, group.clone(), info.name());
+            }
+        });
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (
+                (update_switches, apply_scale_effects, apply_lock_effects).chain().in_set(GameplaySet::Update),
+                reapply_scale_effects.after(restore_checkpoint_scales).in_set(GameplaySet::Movement),
+                update_switch_colors,
+                check_switch_targets.after(update_fallback_groups),
+                draw_switch_links.in_set(EditorSet),
+            ))
+            .add_systems(OnExit(GameState::InGame), reset_switches);
+
+        app.add_yoleck_entity_type(YoleckEntityType::new("Switch").with::<YoleckSwitch>());
+        app.add_yoleck_edit_system(edit_switch);
+        app.yoleck_populate_schedule_mut().add_systems(populate_switch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level::{hazard::HazardBundle, resizable::YoleckScalable};
+
+    #[test]
+    fn hazards_do_not_press_plates() {
+        let mut world = World::new();
+        let player = world.spawn(Player).id();
+        let scalable = world.spawn(YoleckScalable::default().bundle()).id();
+        let hazard = world.spawn(YoleckScalable::default().bundle()).insert(HazardBundle::new(Vec2::ONE)).id();
+
+        let pushers = world.query_filtered::<Entity, PlatePusher>().iter(&world).collect::<HashSet<_>>();
+        assert!(pushers.contains(&player));
+        assert!(pushers.contains(&scalable));
+        assert!(!pushers.contains(&hazard));
+    }
+}
@@ -7,13 +7,16 @@ use bevy_yoleck::{bevy_egui::EguiPlugin, YoleckPluginForEditor, YoleckPluginForG
 
 // SUBMODULES
 mod camera;
+mod input;
 mod level;
+mod menu;
+mod pause;
 mod player;
+mod save;
 
 // GAME STATES
 #[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub enum GameState {
-    // TODO: #[default] Menu,
     #[default]
     Menu,
     InGame,
@@ -21,6 +24,17 @@ pub enum GameState {
     LevelEditor,
 }
 
+/// Whether gameplay is frozen, only meaningful in `GameState::InGame`
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+    LevelComplete,
+    /// Typing an exact scale for the selected group
+    EnteringScale,
+}
+
 // SYSTEM SETS
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub enum GameplaySet {
@@ -32,9 +46,10 @@ pub enum GameplaySet {
 #[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
 pub struct EditorSet;
 
-// TODO : Menu
-fn exit_menu_auto(st: Res<State<GameState>>, mut next_st: ResMut<NextState<GameState>>) {
+/// The editor skips the menu and opens the first level
+fn exit_menu_auto(st: Res<State<GameState>>, mut next_st: ResMut<NextState<GameState>>, mut current_level: ResMut<level::CurrentLevel>) {
     if *st == GameState::Menu {
+        current_level.0 = Some(1);
         next_st.set(GameState::Loading);
     }
 }
@@ -50,6 +65,14 @@ struct Entangled {
     #[argh(switch, short='e')]
     /// use the level editor
     editor: bool,
+
+    #[argh(switch)]
+    /// snap scalable objects straight to their size instead of easing
+    instant_scaling: bool,
+
+    #[argh(option)]
+    /// how quickly scalable objects ease towards their size
+    scale_speed: Option<f32>,
 }
 
 // MAIN
@@ -85,17 +108,27 @@ fn main() {
                     when_game: GameState::InGame,
                 },
                 EguiPlugin,
-            ));
+            ))
+            .add_systems(Startup, exit_menu_auto);
     }
 
     app
-        .add_plugins((camera::Plugin, level::Plugin, player::Plugin, PhysicsPlugins::default(),))
+        .add_plugins((camera::Plugin, input::Plugin, level::Plugin, menu::Plugin, pause::Plugin, player::Plugin, save::Plugin, PhysicsPlugins::default(),))
         .add_state::<GameState>()
-        .add_systems(Startup, exit_menu_auto)
-        .configure_sets(Update, (GameplaySet::Input, GameplaySet::Update, GameplaySet::Movement).chain().run_if(in_state(GameState::InGame)))
+        .add_state::<PauseState>()
+        .configure_sets(Update, (GameplaySet::Input, GameplaySet::Update, GameplaySet::Movement).chain().run_if(in_state(GameState::InGame).and_then(in_state(PauseState::Running))))
         .configure_sets(Update, EditorSet.run_if(in_state(GameState::LevelEditor)))
         .insert_resource(Gravity(Vec2::NEG_Y * 200.));
 
+    let mut tweening = level::resizable::ScaleTweening {
+        instant: args.instant_scaling,
+        ..Default::default()
+    };
+    if let Some(speed) = args.scale_speed {
+        tweening.speed = speed;
+    }
+    app.insert_resource(tweening);
+
     if args.debug {
         // TODO: Debug plugin w/ Egui
         app.add_plugins((bevy_xpbd_2d::plugins::debug::PhysicsDebugPlugin::default(),));
@@ -0,0 +1,342 @@
+//! The main menu, level select and settings screens
+use bevy::{prelude::*, app::AppExit};
+use bevy_yoleck::prelude::*;
+
+use strum::IntoEnumIterator;
+
+use crate::{GameState, input::{Action, InputBindings}, level::{CurrentLevel, LevelIndex, LevelProgress}, save::UserSettings};
+
+const BUTTON_BG: Color = Color::rgb(0.75, 0.75, 0.75);
+const HOVERED_BG: Color = Color::rgb(0.65, 0.65, 0.65);
+const LOCKED_BG: Color = Color::rgb(0.35, 0.35, 0.35);
+const BUTTON_TEXT: Color = Color::rgb(0.1, 0.1, 0.1);
+
+// STATES
+#[derive(States, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MenuScreen {
+    #[default]
+    Closed,
+    Main,
+    LevelSelect,
+    Settings,
+    Controls,
+}
+
+// COMPONENTS
+#[derive(Component, Clone, Copy)]
+pub enum MenuButton {
+    Play,
+    LevelSelect,
+    Settings,
+    Controls,
+    Quit,
+    Back,
+    Level(usize),
+    Fullscreen,
+    Deadzone,
+    ScaleSensitivity,
+    Rebind(Action),
+    ResetBindings,
+}
+
+/// A button for a level the player hasn't reached yet
+#[derive(Component)]
+pub struct Locked;
+
+#[derive(Component)]
+pub struct MenuRoot;
+
+// RESOURCES
+/// The action waiting for a key press to bind to it
+#[derive(Resource, Default)]
+pub struct Rebinding(Option<Action>);
+
+const DEADZONE_PRESETS: [f32; 4] = [0.1, 0.2, 0.3, 0.4];
+const SENSITIVITY_PRESETS: [f32; 5] = [0.5, 1., 1.5, 2., 3.];
+
+// HELPERS
+/// The preset after `current`, wrapping back to the first
+fn next_preset(presets: &[f32], current: f32) -> f32 {
+    presets.iter().copied().find(|&preset| preset > current + f32::EPSILON).unwrap_or(presets[0])
+}
+
+/// Spawns a centred column with a title, returning its root
+pub(crate) fn spawn_screen(cmd: &mut Commands, title: &str, root: impl Bundle, buttons: impl FnOnce(&mut ChildBuilder)) -> Entity {
+    cmd
+        .spawn((NodeBundle {
+            style: Style {
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.),
+                ..Default::default()
+            },
+            ..Default::default()
+        }, root))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(title, TextStyle {
+                font_size: 48.,
+                color: Color::ANTIQUE_WHITE,
+                ..Default::default()
+            }));
+            buttons(parent);
+        })
+        .id()
+}
+
+pub(crate) fn spawn_button(parent: &mut ChildBuilder, label: &str, button: impl Component, locked: bool) {
+    let mut entity = parent.spawn((
+        ButtonBundle {
+            style: Style {
+                width: Val::Px(320.),
+                padding: UiRect::all(Val::Px(6.)),
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            background_color: BackgroundColor(if locked { LOCKED_BG } else { BUTTON_BG }),
+            ..Default::default()
+        },
+        button,
+    ));
+    if locked {
+        entity.insert(Locked);
+    }
+
+    entity.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, TextStyle {
+            font_size: 24.,
+            color: BUTTON_TEXT,
+            ..Default::default()
+        }));
+    });
+}
+
+/// The text for buttons that show a setting, or `None` for fixed labels
+fn button_label(button: MenuButton, settings: &UserSettings, bindings: &InputBindings, rebinding: &Rebinding) -> Option<String> {
+    match button {
+        MenuButton::Fullscreen if settings.fullscreen => Some(String::from("Fullscreen: On")),
+        MenuButton::Fullscreen => Some(String::from("Fullscreen: Off")),
+        MenuButton::Deadzone => Some(format!("Stick Deadzone: {:.0}%", settings.deadzone * 100.)),
+        MenuButton::ScaleSensitivity => Some(format!("Analog Scaling: {:.1}x", settings.scale_sensitivity)),
+        MenuButton::Rebind(action) if rebinding.0 == Some(action) => Some(format!("{}: press a key", action.name())),
+        MenuButton::Rebind(action) => Some(format!("{}: {}", action.name(), bindings.describe(action))),
+        _ => None,
+    }
+}
+
+// SYSTEMS
+/// Opens the main screen, unless another screen was asked for on the way into the menu
+fn open_menu(current: Res<State<MenuScreen>>, mut screen: ResMut<NextState<MenuScreen>>) {
+    if *current == MenuScreen::Closed && screen.0.is_none() {
+        screen.set(MenuScreen::Main);
+    }
+}
+
+fn close_menu(mut screen: ResMut<NextState<MenuScreen>>) {
+    screen.set(MenuScreen::Closed);
+}
+
+fn setup_main(mut cmd: Commands) {
+    spawn_screen(&mut cmd, "Entangled", MenuRoot, |parent| {
+        spawn_button(parent, "Play", MenuButton::Play, false);
+        spawn_button(parent, "Level Select", MenuButton::LevelSelect, false);
+        spawn_button(parent, "Settings", MenuButton::Settings, false);
+        #[cfg(not(target_arch = "wasm32"))]
+        spawn_button(parent, "Quit", MenuButton::Quit, false);
+    });
+}
+
+fn setup_level_select(
+    mut cmd: Commands,
+    level_index: Res<LevelIndex>,
+    level_index_assets: Res<Assets<YoleckLevelIndex>>,
+    progress: Res<LevelProgress>,
+) {
+    let level_count = level_index_assets.get(&level_index.0).map_or(0, |index| index.len());
+
+    spawn_screen(&mut cmd, "Level Select", MenuRoot, |parent| {
+        for level in 1..=level_count {
+            spawn_button(parent, &format!("Level {level}"), MenuButton::Level(level), level > progress.unlocked);
+        }
+        spawn_button(parent, "Back", MenuButton::Back, false);
+    });
+}
+
+fn setup_settings(mut cmd: Commands, settings: Res<UserSettings>, bindings: Res<InputBindings>, rebinding: Res<Rebinding>) {
+    spawn_screen(&mut cmd, "Settings", MenuRoot, |parent| {
+        for button in [MenuButton::Fullscreen, MenuButton::Deadzone, MenuButton::ScaleSensitivity] {
+            let label = button_label(button, &settings, &bindings, &rebinding).unwrap_or_default();
+            spawn_button(parent, &label, button, false);
+        }
+        spawn_button(parent, "Controls", MenuButton::Controls, false);
+        spawn_button(parent, "Back", MenuButton::Back, false);
+    });
+}
+
+/// Lays the actions out two to a row, since there are too many for one column
+fn setup_controls(mut cmd: Commands, settings: Res<UserSettings>, bindings: Res<InputBindings>, rebinding: Res<Rebinding>) {
+    spawn_screen(&mut cmd, "Controls", MenuRoot, |parent| {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(656.),
+                    flex_wrap: FlexWrap::Wrap,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(8.),
+                    column_gap: Val::Px(16.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .with_children(|parent| {
+                for button in Action::iter().map(MenuButton::Rebind) {
+                    let label = button_label(button, &settings, &bindings, &rebinding).unwrap_or_default();
+                    spawn_button(parent, &label, button, false);
+                }
+            });
+        spawn_button(parent, "Reset Controls", MenuButton::ResetBindings, false);
+        spawn_button(parent, "Back", MenuButton::Back, false);
+    });
+}
+
+fn despawn_screen(mut cmd: Commands, q: Query<Entity, With<MenuRoot>>) {
+    for entity in q.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+/// Starts a level from the buttons that lead into one
+fn handle_level_buttons(
+    buttons: Query<(&Interaction, &MenuButton), (Changed<Interaction>, Without<Locked>)>,
+    mut state: ResMut<NextState<GameState>>,
+    mut current_level: ResMut<CurrentLevel>,
+    progress: Res<LevelProgress>,
+    level_index: Res<LevelIndex>,
+    level_index_assets: Res<Assets<YoleckLevelIndex>>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match *button {
+            MenuButton::Play => {
+                let level_count = level_index_assets.get(&level_index.0).map_or(1, |index| index.len());
+                current_level.0 = Some(progress.unlocked.clamp(1, level_count.max(1)));
+                state.set(GameState::Loading);
+            },
+            MenuButton::Level(level) => {
+                current_level.0 = Some(level);
+                state.set(GameState::Loading);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Handles every other button, which move between screens or change settings
+fn handle_menu_buttons(
+    buttons: Query<(&Interaction, &MenuButton), (Changed<Interaction>, Without<Locked>)>,
+    mut screen: ResMut<NextState<MenuScreen>>,
+    current_screen: Res<State<MenuScreen>>,
+    mut settings: ResMut<UserSettings>,
+    mut bindings: ResMut<InputBindings>,
+    mut rebinding: ResMut<Rebinding>,
+    mut app_exit_evw: EventWriter<AppExit>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match *button {
+            MenuButton::Play | MenuButton::Level(_) => {},
+            MenuButton::LevelSelect => screen.set(MenuScreen::LevelSelect),
+            MenuButton::Settings => screen.set(MenuScreen::Settings),
+            MenuButton::Controls => screen.set(MenuScreen::Controls),
+            MenuButton::Back if *current_screen == MenuScreen::Controls => screen.set(MenuScreen::Settings),
+            MenuButton::Back => screen.set(MenuScreen::Main),
+            MenuButton::Quit => app_exit_evw.send(AppExit),
+            MenuButton::Fullscreen => settings.fullscreen = !settings.fullscreen,
+            MenuButton::Deadzone => settings.deadzone = next_preset(&DEADZONE_PRESETS, settings.deadzone),
+            MenuButton::ScaleSensitivity => settings.scale_sensitivity = next_preset(&SENSITIVITY_PRESETS, settings.scale_sensitivity),
+            MenuButton::Rebind(action) => rebinding.0 = Some(action),
+            MenuButton::ResetBindings => *bindings = InputBindings::default(),
+        }
+    }
+}
+
+fn update_button_colors(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>, Without<Locked>)>,
+) {
+    for (interaction, mut bg) in buttons.iter_mut() {
+        bg.0 = match interaction {
+            Interaction::Hovered | Interaction::Pressed => HOVERED_BG,
+            Interaction::None => BUTTON_BG,
+        };
+    }
+}
+
+/// Binds the next key pressed to the action being rebound
+fn capture_rebinding(keys: Res<Input<KeyCode>>, mut rebinding: ResMut<Rebinding>, mut bindings: ResMut<InputBindings>) {
+    let Some(action) = rebinding.0 else { return };
+    let Some(&key) = keys.get_just_pressed().next() else { return };
+
+    bindings.bind(action, vec![key]);
+    rebinding.0 = None;
+}
+
+fn cancel_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
+
+fn update_button_labels(
+    settings: Res<UserSettings>,
+    bindings: Res<InputBindings>,
+    rebinding: Res<Rebinding>,
+    buttons: Query<(&MenuButton, &Children)>,
+    mut texts: Query<&mut Text>,
+) {
+    for (&button, children) in buttons.iter() {
+        let Some(label) = button_label(button, &settings, &bindings, &rebinding) else { continue };
+
+        let mut iter = texts.iter_many_mut(children);
+        while let Some(mut text) = iter.fetch_next() {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_state::<MenuScreen>()
+            .init_resource::<Rebinding>()
+            .add_systems(OnEnter(GameState::Menu), open_menu)
+            .add_systems(OnExit(GameState::Menu), close_menu)
+            .add_systems(OnEnter(MenuScreen::Main), setup_main)
+            .add_systems(OnEnter(MenuScreen::LevelSelect), setup_level_select)
+            .add_systems(OnEnter(MenuScreen::Settings), setup_settings)
+            .add_systems(OnEnter(MenuScreen::Controls), setup_controls)
+            .add_systems(OnExit(MenuScreen::Main), despawn_screen)
+            .add_systems(OnExit(MenuScreen::LevelSelect), despawn_screen)
+            .add_systems(OnExit(MenuScreen::Settings), despawn_screen)
+            .add_systems(OnExit(MenuScreen::Controls), (despawn_screen, cancel_rebinding))
+            .add_systems(Update, update_button_colors)
+            .add_systems(Update, (
+                handle_level_buttons,
+                handle_menu_buttons,
+                capture_rebinding,
+                update_button_labels.run_if(
+                    resource_changed::<UserSettings>()
+                        .or_else(resource_changed::<InputBindings>())
+                        .or_else(resource_changed::<Rebinding>())
+                ),
+            ).run_if(not(in_state(MenuScreen::Closed))));
+    }
+}
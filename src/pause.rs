@@ -0,0 +1,111 @@
+//! The pause overlay, which freezes gameplay while it is open
+use bevy::{prelude::*, input::mouse::MouseWheel};
+use bevy_xpbd_2d::prelude::*;
+
+use crate::{GameState, PauseState, input::{Action, Actions}, level::CurrentLevel, menu::{self, MenuScreen}};
+
+pub(crate) const OVERLAY_BG: Color = Color::rgba(0., 0., 0., 0.6);
+
+// COMPONENTS
+#[derive(Component, Clone, Copy)]
+pub enum PauseButton {
+    Resume,
+    Restart,
+    LevelSelect,
+    Quit,
+}
+
+#[derive(Component)]
+pub struct PauseRoot;
+
+// SYSTEMS
+fn toggle_pause(
+    actions: Actions,
+    current_level: Res<CurrentLevel>,
+    pause: Res<State<PauseState>>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+) {
+    // Playtesting from the editor has no level to restart or menu to return to
+    if current_level.0.is_none() || !actions.just_pressed(Action::Pause) {
+        return;
+    }
+
+    match pause.get() {
+        PauseState::Running => next_pause.set(PauseState::Paused),
+        PauseState::Paused => next_pause.set(PauseState::Running),
+        PauseState::LevelComplete | PauseState::EnteringScale => {},
+    }
+}
+
+fn freeze_physics(mut physics_loop: ResMut<PhysicsLoop>) {
+    physics_loop.pause();
+}
+
+/// Also drops any scrolling done while frozen so it can't change a scale on resume
+fn thaw_physics(mut physics_loop: ResMut<PhysicsLoop>, mut mouse_wheel: ResMut<Events<MouseWheel>>) {
+    physics_loop.resume();
+    mouse_wheel.clear();
+}
+
+fn setup_overlay(mut cmd: Commands) {
+    let root = menu::spawn_screen(&mut cmd, "Paused", PauseRoot, |parent| {
+        menu::spawn_button(parent, "Resume", PauseButton::Resume, false);
+        menu::spawn_button(parent, "Restart Level", PauseButton::Restart, false);
+        menu::spawn_button(parent, "Level Select", PauseButton::LevelSelect, false);
+        menu::spawn_button(parent, "Quit to Menu", PauseButton::Quit, false);
+    });
+    cmd.entity(root).insert((BackgroundColor(OVERLAY_BG), ZIndex::Global(1)));
+}
+
+fn despawn_overlay(mut cmd: Commands, q: Query<Entity, With<PauseRoot>>) {
+    for entity in q.iter() {
+        cmd.entity(entity).despawn_recursive();
+    }
+}
+
+fn unpause(mut next_pause: ResMut<NextState<PauseState>>) {
+    next_pause.set(PauseState::Running);
+}
+
+fn handle_pause_buttons(
+    buttons: Query<(&Interaction, &PauseButton), Changed<Interaction>>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+    mut state: ResMut<NextState<GameState>>,
+    mut screen: ResMut<NextState<MenuScreen>>,
+) {
+    for (interaction, button) in buttons.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        next_pause.set(PauseState::Running);
+        match *button {
+            PauseButton::Resume => {},
+            // Leaving for `Loading` keeps the current level, so it is loaded again from scratch
+            PauseButton::Restart => state.set(GameState::Loading),
+            PauseButton::LevelSelect => {
+                state.set(GameState::Menu);
+                screen.set(MenuScreen::LevelSelect);
+            },
+            PauseButton::Quit => state.set(GameState::Menu),
+        }
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnExit(PauseState::Running), freeze_physics)
+            .add_systems(OnEnter(PauseState::Running), thaw_physics)
+            .add_systems(OnEnter(PauseState::Paused), setup_overlay)
+            .add_systems(OnExit(PauseState::Paused), despawn_overlay)
+            .add_systems(OnExit(GameState::InGame), unpause)
+            .add_systems(Update, (
+                toggle_pause.run_if(in_state(GameState::InGame)),
+                handle_pause_buttons.run_if(in_state(PauseState::Paused)),
+            ));
+    }
+}
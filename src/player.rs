@@ -1,11 +1,17 @@
 use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
 use bevy_xpbd_2d::prelude::*;
 
-use crate::{GameState, GameplaySet, camera::WINDOW_BOTTOM_LEFT};
+use crate::{GameState, GameplaySet, camera::WINDOW_BOTTOM_LEFT, level::spawn_point::SpawnPoint};
 
-use self::movement::MovementBundle;
+use self::{animation::{PlayerAnimation, PlayerSheets}, movement::MovementBundle};
+
+/// Where the player starts in levels without a spawn point
+pub const DEFAULT_SPAWN: Vec2 = Vec2::new(WINDOW_BOTTOM_LEFT.x + 100., -100.);
+/// How large the player is drawn, before any squash from animations
+pub const PLAYER_SIZE: Vec2 = Vec2::new(25., 45.);
 
 // SUBMODULES
+pub mod animation;
 pub mod movement;
 pub mod respawn;
 
@@ -15,7 +21,8 @@ pub struct Player;
 
 #[derive(Bundle)]
 pub struct PlayerBundle {
-    sprite: SpriteBundle,
+    sprite: SpriteSheetBundle,
+    animation: PlayerAnimation,
     movement: MovementBundle,
     marker: Player,
 }
@@ -24,18 +31,25 @@ pub struct PlayerBundle {
 /// The system which adds the player to the game
 pub fn setup(
     mut cmd: Commands,
-    assets: Res<AssetServer>,
+    sheets: Res<PlayerSheets>,
+    spawn_points: Query<&SpawnPoint>,
 ) {
+    let position = spawn_points.iter().next().map_or(DEFAULT_SPAWN, |spawn_point| spawn_point.0);
+
+    let (texture_atlas, index) = sheets.first_frame();
+
     cmd.spawn((PlayerBundle {
-        sprite: SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(25., 45.)),
+        sprite: SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                index,
+                custom_size: Some(PLAYER_SIZE),
                 ..Default::default()
             },
-            transform: Transform::from_xyz(WINDOW_BOTTOM_LEFT.x + 100., -100., 10.),
-            texture: assets.load("sprites/player.png"),
+            transform: Transform::from_translation(position.extend(10.)),
+            texture_atlas,
             ..Default::default()
         },
+        animation: PlayerAnimation::default(),
         movement: MovementBundle::new(Collider::capsule(20.0, 12.5)),
         marker: Player,
     },));
@@ -57,7 +71,7 @@ impl bevy::app::Plugin for Plugin {
         app
             .add_systems(OnEnter(GameState::InGame), (setup,))
             .add_systems(OnExit(GameState::InGame), (stop,))
-            .add_plugins((movement::Plugin, respawn::Plugin));
+            .add_plugins((animation::Plugin, movement::Plugin, respawn::Plugin));
     }
 }
 
@@ -0,0 +1,207 @@
+//! Picks and plays the player's sprite animation from how they are moving
+use bevy::prelude::*;
+use bevy_xpbd_2d::prelude::*;
+
+use crate::{GameplaySet, level::LevelRun};
+
+use super::{Player, PLAYER_SIZE, movement::Grounded};
+
+/// The size of one frame in every player sprite sheet
+const FRAME_SIZE: Vec2 = Vec2::splat(16.);
+/// Horizontal speeds below this count as standing still
+const WALK_SPEED: f32 = 10.;
+
+// SHEETS
+#[derive(Clone, Copy)]
+enum Sheet {
+    Standing,
+    Walking,
+}
+
+impl Sheet {
+    /// The image and number of frames in a row
+    fn source(self) -> (&'static str, usize) {
+        match self {
+            Sheet::Standing => ("sprites/player.png", 1),
+            Sheet::Walking => ("sprites/player_walking.png", 1),
+        }
+    }
+}
+
+/// The frames of one animation state and how they are played
+struct Clip {
+    frames: &'static [(Sheet, usize)],
+    /// In seconds
+    frame_time: f32,
+    looping: bool,
+    tint: Color,
+    /// Multiplies the player's size, for squashing and stretching
+    squash: Vec2,
+}
+
+impl Clip {
+    const fn new(frames: &'static [(Sheet, usize)], frame_time: f32, looping: bool) -> Self {
+        Self { frames, frame_time, looping, tint: Color::WHITE, squash: Vec2::ONE }
+    }
+
+    fn frame(&self, elapsed: f32) -> (Sheet, usize) {
+        let frame = (elapsed / self.frame_time) as usize;
+        let frame = if self.looping { frame % self.frames.len() } else { frame.min(self.frames.len() - 1) };
+        self.frames[frame]
+    }
+
+    /// Looping clips never finish
+    fn finished(&self, elapsed: f32) -> bool {
+        !self.looping && elapsed >= self.frame_time * self.frames.len() as f32
+    }
+}
+
+// COMPONENTS
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum AnimationState {
+    #[default]
+    Idle,
+    Walk,
+    Jump,
+    Fall,
+    /// Played once on touching the ground
+    Land,
+    /// Played once after dying, at the respawn point
+    Death,
+}
+
+impl AnimationState {
+    fn clip(self) -> Clip {
+        match self {
+            AnimationState::Idle => Clip::new(&[(Sheet::Standing, 0)], 0.5, true),
+            AnimationState::Walk => Clip::new(&[(Sheet::Walking, 0), (Sheet::Standing, 0)], 0.15, true),
+            AnimationState::Jump => Clip { squash: Vec2::new(0.9, 1.1), ..Clip::new(&[(Sheet::Walking, 0)], 0.1, false) },
+            AnimationState::Fall => Clip::new(&[(Sheet::Standing, 0)], 0.1, false),
+            AnimationState::Land => Clip { squash: Vec2::new(1.15, 0.9), ..Clip::new(&[(Sheet::Standing, 0)], 0.1, false) },
+            AnimationState::Death => Clip { tint: Color::rgb(1., 0.3, 0.3), ..Clip::new(&[(Sheet::Standing, 0)], 0.4, false) },
+        }
+    }
+}
+
+/// Where the player is in their animation, and what it was chosen from last frame
+#[derive(Component, Default)]
+pub struct PlayerAnimation {
+    state: AnimationState,
+    /// Seconds since entering `state`
+    elapsed: f32,
+    was_grounded: bool,
+    /// `None` until the first frame, so a player spawned mid-run doesn't start out dying
+    deaths_seen: Option<u32>,
+}
+
+impl PlayerAnimation {
+    fn next_state(&self, grounded: bool, velocity: Vec2, died: bool) -> AnimationState {
+        let finished = self.state.clip().finished(self.elapsed);
+
+        if died || (self.state == AnimationState::Death && !finished) {
+            AnimationState::Death
+        } else if grounded && (!self.was_grounded || (self.state == AnimationState::Land && !finished)) {
+            AnimationState::Land
+        } else if !grounded {
+            if velocity.y > 0. { AnimationState::Jump } else { AnimationState::Fall }
+        } else if velocity.x.abs() > WALK_SPEED {
+            AnimationState::Walk
+        } else {
+            AnimationState::Idle
+        }
+    }
+}
+
+// RESOURCES
+/// An atlas for each player sprite sheet
+#[derive(Resource)]
+pub struct PlayerSheets {
+    standing: Handle<TextureAtlas>,
+    walking: Handle<TextureAtlas>,
+}
+
+impl PlayerSheets {
+    fn get(&self, sheet: Sheet) -> Handle<TextureAtlas> {
+        match sheet {
+            Sheet::Standing => self.standing.clone(),
+            Sheet::Walking => self.walking.clone(),
+        }
+    }
+
+    /// The atlas and index the player starts with
+    pub fn first_frame(&self) -> (Handle<TextureAtlas>, usize) {
+        let (sheet, index) = AnimationState::default().clip().frame(0.);
+        (self.get(sheet), index)
+    }
+}
+
+impl FromWorld for PlayerSheets {
+    fn from_world(world: &mut World) -> Self {
+        let mut load = |sheet: Sheet| {
+            let (path, columns) = sheet.source();
+            let texture = world.resource::<AssetServer>().load(path);
+            world
+                .resource_mut::<Assets<TextureAtlas>>()
+                .add(TextureAtlas::from_grid(texture, FRAME_SIZE, columns, 1, None, None))
+        };
+
+        Self {
+            standing: load(Sheet::Standing),
+            walking: load(Sheet::Walking),
+        }
+    }
+}
+
+// SYSTEMS
+fn animate_player(
+    time: Res<Time>,
+    run: Res<LevelRun>,
+    sheets: Res<PlayerSheets>,
+    mut q: Query<(
+        &mut PlayerAnimation,
+        &mut TextureAtlasSprite,
+        &mut Handle<TextureAtlas>,
+        &LinearVelocity,
+        Has<Grounded>,
+    ), With<Player>>,
+) {
+    for (mut animation, mut sprite, mut atlas, velocity, grounded) in &mut q {
+        let died = animation.deaths_seen.is_some_and(|seen| run.deaths > seen);
+        animation.deaths_seen = Some(run.deaths);
+
+        let state = animation.next_state(grounded, velocity.0, died);
+        if state == animation.state {
+            animation.elapsed += time.delta_seconds();
+        } else {
+            animation.state = state;
+            animation.elapsed = 0.;
+        }
+        animation.was_grounded = grounded;
+
+        let clip = state.clip();
+        let (sheet, index) = clip.frame(animation.elapsed);
+        let handle = sheets.get(sheet);
+        if *atlas != handle {
+            *atlas = handle;
+        }
+        sprite.index = index;
+        sprite.color = clip.tint;
+        sprite.custom_size = Some(PLAYER_SIZE * clip.squash);
+
+        // Keep facing the same way when stopping
+        if velocity.x.abs() > WALK_SPEED {
+            sprite.flip_x = velocity.x < 0.;
+        }
+    }
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<PlayerSheets>()
+            .add_systems(Update, animate_player.in_set(GameplaySet::Movement));
+    }
+}
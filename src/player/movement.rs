@@ -2,11 +2,16 @@
 use bevy::prelude::*;
 use bevy_xpbd_2d::{prelude::*, math::*};
 
-use crate::GameplaySet;
+use crate::{GameplaySet, input::{Action, Actions}};
 
 // EVENTS
 #[derive(Event)]
-pub struct MovementEvent(Vector);
+pub enum MovementEvent {
+    /// Accelerate horizontally, from -1 to 1
+    Move(Scalar),
+    Jump,
+    JumpReleased,
+}
 
 // COMPONENTS
 #[derive(Component)]
@@ -19,10 +24,19 @@ pub struct Grounded;
 /// A component used to handle player inputs
 #[derive(Component)]
 pub struct Movement {
-    acceleration: Scalar,
-    damping_factor: Scalar,
-    jump_impulse: Scalar,
-    max_slope_angle: Option<Scalar>,
+    pub acceleration: Scalar,
+    /// The fraction of horizontal speed kept every 60th of a second
+    pub damping_factor: Scalar,
+    /// The fastest the controller can accelerate itself horizontally
+    pub max_speed: Scalar,
+    pub jump_impulse: Scalar,
+    pub max_slope_angle: Option<Scalar>,
+    /// How long after walking off a ledge a jump is still allowed, in seconds
+    pub coyote_time: Scalar,
+    /// How long before landing a jump press is remembered, in seconds
+    pub jump_buffer: Scalar,
+    /// What upward velocity is multiplied by when jump is released early
+    pub jump_cut: Scalar,
 }
 
 impl Default for Movement {
@@ -30,12 +44,46 @@ impl Default for Movement {
         Self {
             acceleration: 600.,
             damping_factor: 0.9,
+            max_speed: 100.,
             jump_impulse: 150.,
             max_slope_angle: Some(PI * 0.45),
+            coyote_time: 0.1,
+            jump_buffer: 0.1,
+            jump_cut: 0.5,
         }
     }
 }
 
+impl Movement {
+    /// Advances horizontal velocity by `delta_time` while accelerating towards `direction`.
+    /// Damping is solved exactly rather than stepped, so the result doesn't depend on frame rate.
+    pub fn horizontal_velocity(&self, velocity: Scalar, direction: Scalar, delta_time: Scalar) -> Scalar {
+        let damping_rate = -self.damping_factor.ln() * 60.;
+        let result = if damping_rate > 0. {
+            let terminal = direction * self.acceleration / damping_rate;
+            terminal + (velocity - terminal) * (-damping_rate * delta_time).exp()
+        } else {
+            // Without damping there is no terminal speed, so only `max_speed` stops the controller
+            velocity + direction * self.acceleration * delta_time
+        };
+
+        // Faster speeds from elsewhere still wear off through damping, they just can't be added to
+        let limit = self.max_speed.max(velocity.abs());
+        result.clamp(-limit, limit)
+    }
+}
+
+/// Timers for coyote time and jump buffering
+#[derive(Component, Default)]
+pub struct JumpState {
+    /// Seconds since the controller was last grounded, or `None` once that chance to jump is used
+    since_grounded: Option<Scalar>,
+    /// Seconds since jump was pressed, or `None` if there's no press waiting
+    since_pressed: Option<Scalar>,
+    /// Whether the controller is still rising from a jump, so releasing jump can cut it short
+    rising: bool,
+}
+
 // BUNDLE
 #[derive(Bundle)]
 pub struct MovementBundle {
@@ -47,6 +95,7 @@ pub struct MovementBundle {
     friction: Friction,
     restitution: Restitution,
     movement: Movement,
+    jump_state: JumpState,
 }
 
 impl MovementBundle {
@@ -64,6 +113,7 @@ impl MovementBundle {
             friction: Friction::ZERO.with_combine_rule(CoefficientCombine::Min),
             restitution: Restitution::ZERO.with_combine_rule(CoefficientCombine::Min),
             movement: Movement::default(),
+            jump_state: JumpState::default(),
         }
     }
 }
@@ -71,20 +121,19 @@ impl MovementBundle {
 // SYSTEMS
 fn keyboard_input(
     mut movement_event_writer: EventWriter<MovementEvent>,
-    keyboard_input: Res<Input<KeyCode>>,
+    actions: Actions,
 ) {
-    let mut direction = Vector::ZERO;
-
-    let left = keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]);
-    let right = keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]);
-    direction.x = (right as i8 - left as i8) as Scalar;
+    let direction = actions.movement() as Scalar;
+    if direction != 0. {
+        movement_event_writer.send(MovementEvent::Move(direction));
+    }
 
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        direction.y = 1.0;
+    if actions.just_pressed(Action::Jump) {
+        movement_event_writer.send(MovementEvent::Jump);
     }
 
-    if direction != Vector::ZERO {
-        movement_event_writer.send(MovementEvent(direction))
+    if actions.just_released(Action::Jump) {
+        movement_event_writer.send(MovementEvent::JumpReleased);
     }
 }
 
@@ -117,26 +166,61 @@ fn movement(
     mut movement_event_reader: EventReader<MovementEvent>,
     mut controllers: Query<(
         &Movement,
+        &mut JumpState,
         &mut LinearVelocity,
         Has<Grounded>,
     ), With<CharacterController>>,
 ) {
     let delta_time = time.delta_seconds_f64().adjust_precision();
 
-    for event in movement_event_reader.read() {
-        for (&Movement { acceleration, jump_impulse, .. }, mut linear_velocity, is_grounded) in &mut controllers {
-            linear_velocity.x += event.0.x * acceleration * delta_time;
+    for (_, mut jump, linear_velocity, is_grounded) in &mut controllers {
+        if jump.rising && linear_velocity.y <= 0. {
+            jump.rising = false;
+        }
+
+        // The ground caster still hits just after take-off, which mustn't count as landing
+        jump.since_grounded = if is_grounded && !jump.rising {
+            Some(0.)
+        } else {
+            jump.since_grounded.map(|time| time + delta_time)
+        };
+        jump.since_pressed = jump.since_pressed.map(|time| time + delta_time);
+    }
 
-            if is_grounded && event.0.y != 0. {
-                linear_velocity.y = jump_impulse;
+    let mut direction: Scalar = 0.;
+    for event in movement_event_reader.read() {
+        for (&Movement { jump_cut, .. }, mut jump, mut linear_velocity, _) in &mut controllers {
+            match *event {
+                MovementEvent::Move(_) => {},
+                MovementEvent::Jump => jump.since_pressed = Some(0.),
+                MovementEvent::JumpReleased if jump.rising => {
+                    linear_velocity.y *= jump_cut;
+                    jump.rising = false;
+                },
+                MovementEvent::JumpReleased => {},
             }
         }
+
+        if let MovementEvent::Move(amount) = *event {
+            direction = (direction + amount).clamp(-1., 1.);
+        }
     }
-}
 
-fn apply_movement_damping(mut query: Query<(&Movement, &mut LinearVelocity)>) {
-    for (Movement { damping_factor, .. }, mut linear_velocity) in &mut query {
-        linear_velocity.x *= damping_factor;
+    for (movement, mut jump, mut linear_velocity, _) in &mut controllers {
+        let &Movement { jump_impulse, coyote_time, jump_buffer, .. } = movement;
+        linear_velocity.x = movement.horizontal_velocity(linear_velocity.x, direction, delta_time);
+
+        let buffered = jump.since_pressed.is_some_and(|time| time <= jump_buffer);
+        let can_jump = jump.since_grounded.is_some_and(|time| time <= coyote_time);
+
+        if buffered && can_jump {
+            linear_velocity.y = jump_impulse;
+            jump.since_grounded = None;
+            jump.since_pressed = None;
+            jump.rising = true;
+        } else if !buffered {
+            jump.since_pressed = None;
+        }
     }
 }
 
@@ -150,7 +234,166 @@ impl bevy::app::Plugin for Plugin {
             .add_systems(Update, (
                 keyboard_input.in_set(GameplaySet::Input),
                 update_grounded.after(apply_deferred).in_set(GameplaySet::Update),
-                (movement, apply_movement_damping).chain().in_set(GameplaySet::Movement),
+                movement.in_set(GameplaySet::Movement),
             ).chain());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    /// A headless controller stepped at `fps`, with nothing but `movement` moving it
+    fn controller(fps: u32, movement: Movement) -> (App, Entity) {
+        let mut app = App::new();
+        app
+            .add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1. / fps as f64)))
+            .add_event::<MovementEvent>()
+            .add_systems(Update, super::movement);
+
+        let player = app.world.spawn((CharacterController, movement, JumpState::default(), LinearVelocity::default())).id();
+
+        // The first update only starts the clock
+        app.update();
+        (app, player)
+    }
+
+    fn step(app: &mut App, frames: u32) {
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+
+    fn vertical_velocity(app: &App, player: Entity) -> Scalar {
+        app.world.get::<LinearVelocity>(player).unwrap().y
+    }
+
+    /// Runs a controller headlessly at `fps`, holding right for a second and then letting go,
+    /// and samples its horizontal velocity every twelfth of a second
+    fn trajectory(fps: u32, movement: Movement) -> Vec<Scalar> {
+        let (mut app, player) = controller(fps, movement);
+
+        let mut samples = Vec::new();
+        for frame in 1..=fps * 2 {
+            if frame <= fps {
+                app.world.send_event(MovementEvent::Move(1.));
+            }
+            app.update();
+
+            if frame % (fps / 12) == 0 {
+                samples.push(app.world.get::<LinearVelocity>(player).unwrap().x);
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn trajectory_is_independent_of_frame_rate() {
+        let reference = trajectory(60, Movement::default());
+
+        for fps in [48, 144, 240] {
+            let samples = trajectory(fps, Movement::default());
+            assert_eq!(samples.len(), reference.len());
+
+            for (time, (sample, expected)) in samples.iter().zip(&reference).enumerate() {
+                assert!((sample - expected).abs() < 1e-2, "{fps} fps differs at sample {time}: {sample} != {expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn speed_is_capped() {
+        // Well below the default terminal speed, so the cap is what stops the controller
+        let movement = Movement { max_speed: 50., ..Default::default() };
+        let samples = trajectory(60, movement);
+
+        assert!(samples.iter().all(|speed| *speed <= 50.), "{samples:?}");
+        assert!(samples.iter().any(|speed| *speed == 50.), "{samples:?}");
+    }
+
+    #[test]
+    fn undamped_speed_is_capped() {
+        let movement = Movement { damping_factor: 1., ..Default::default() };
+        let samples = trajectory(60, movement);
+
+        assert!(samples.iter().all(|speed| speed.is_finite() && *speed <= 100.), "{samples:?}");
+        // Nothing slows the controller down once it lets go
+        assert_eq!(samples.last(), Some(&100.));
+    }
+
+    #[test]
+    fn jumps_within_coyote_time_after_leaving_a_ledge() {
+        let jump_impulse = Movement::default().jump_impulse;
+
+        for (frames_off_ledge, jumps) in [(3, true), (9, false)] {
+            let (mut app, player) = controller(60, Movement::default());
+            app.world.entity_mut(player).insert(Grounded);
+            step(&mut app, 1);
+
+            app.world.entity_mut(player).remove::<Grounded>();
+            step(&mut app, frames_off_ledge);
+            app.world.send_event(MovementEvent::Jump);
+            step(&mut app, 1);
+
+            let expected = if jumps { jump_impulse } else { 0. };
+            assert_eq!(vertical_velocity(&app, player), expected, "{frames_off_ledge} frames after leaving the ledge");
+        }
+    }
+
+    #[test]
+    fn buffered_jumps_fire_on_landing() {
+        let jump_impulse = Movement::default().jump_impulse;
+
+        for (frames_before_landing, jumps) in [(3, true), (9, false)] {
+            let (mut app, player) = controller(60, Movement::default());
+            app.world.send_event(MovementEvent::Jump);
+            step(&mut app, frames_before_landing);
+            assert_eq!(vertical_velocity(&app, player), 0., "jumped before landing");
+
+            app.world.entity_mut(player).insert(Grounded);
+            step(&mut app, 1);
+
+            let expected = if jumps { jump_impulse } else { 0. };
+            assert_eq!(vertical_velocity(&app, player), expected, "pressed {frames_before_landing} frames before landing");
+        }
+    }
+
+    #[test]
+    fn releasing_jump_early_cuts_it_short() {
+        let movement = Movement::default();
+        let (jump_impulse, jump_cut) = (movement.jump_impulse, movement.jump_cut);
+        let (mut app, player) = controller(60, movement);
+        app.world.entity_mut(player).insert(Grounded);
+        app.world.send_event(MovementEvent::Jump);
+        step(&mut app, 2);
+        assert_eq!(vertical_velocity(&app, player), jump_impulse);
+
+        app.world.send_event(MovementEvent::JumpReleased);
+        step(&mut app, 1);
+        assert_eq!(vertical_velocity(&app, player), jump_impulse * jump_cut);
+
+        // Only the first release cuts the jump
+        app.world.send_event(MovementEvent::JumpReleased);
+        step(&mut app, 1);
+        assert_eq!(vertical_velocity(&app, player), jump_impulse * jump_cut);
+    }
+
+    #[test]
+    fn releasing_jump_after_the_peak_does_nothing() {
+        let (mut app, player) = controller(60, Movement::default());
+        app.world.entity_mut(player).insert(Grounded);
+        app.world.send_event(MovementEvent::Jump);
+        step(&mut app, 1);
+
+        app.world.entity_mut(player).remove::<Grounded>();
+        app.world.get_mut::<LinearVelocity>(player).unwrap().y = -20.;
+        app.world.send_event(MovementEvent::JumpReleased);
+        step(&mut app, 1);
+        assert_eq!(vertical_velocity(&app, player), -20.);
+    }
+}
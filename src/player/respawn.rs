@@ -1,32 +1,84 @@
 //! Death & Respawn logic
 use bevy::prelude::*;
-use bevy_xpbd_2d::prelude::*;
+use bevy_xpbd_2d::{prelude::*, math::*};
 
-use crate::GameplaySet;
+use crate::{GameplaySet, GameState, PauseState, level::{LevelRun, checkpoint::ActiveCheckpoint, hazard::Hazard, resizable::Scalable, scroll_stop::LevelBounds, settings::{CrushBehaviour, LevelSettings}, spawn_point::SpawnPoint}};
 
-use super::Player;
+use super::{Player, DEFAULT_SPAWN};
+
+/// How much of the player's collider must be inside a scalable object to count as squished
+const SQUISH_TOLERANCE: Scalar = 0.8;
 
 // EVENTS
 #[derive(Event)]
 pub struct Respawn;
 
+/// Sent after a physics step that left the player stuck inside a scalable object
+#[derive(Event)]
+pub struct Squished;
+
 // SYSTEMS
-pub fn check_out_of_bounds(q: Query<&Transform, With<Player>>, mut evw: EventWriter<Respawn>) {
+pub fn check_out_of_bounds(
+    q: Query<&Transform, With<Player>>,
+    bounds_query: Query<&LevelBounds>,
+    mut run: ResMut<LevelRun>,
+    mut evw: EventWriter<Respawn>,
+) {
+    let bounds = bounds_query.get_single().copied().unwrap_or_default();
+
     q.for_each(|transform| {
-        if transform.translation.y < crate::camera::WINDOW_BOTTOM_LEFT.y {
+        if transform.translation.y < bounds.kill_plane() {
+            run.deaths += 1;
             evw.send(Respawn)
         }
     })
 }
 
-// TODO: Check if player is squished
+/// Runs after the physics step, so any overlap left is one the solver couldn't push the player out of
+pub fn check_squished(
+    spatial_query: SpatialQuery,
+    player_query: Query<(Entity, &Collider, &Position, &Rotation), With<Player>>,
+    scalable_query: Query<(), (With<Scalable>, Without<Hazard>)>,
+    settings: Query<&LevelSettings>,
+    mut run: ResMut<LevelRun>,
+    mut squished_evw: EventWriter<Squished>,
+    mut respawn_evw: EventWriter<Respawn>,
+) {
+    let Ok((player, collider, position, rotation)) = player_query.get_single() else { return };
+
+    let mut shape = collider.clone();
+    shape.set_scale(Vector::ONE * SQUISH_TOLERANCE, 10);
+
+    let squished = spatial_query
+        .shape_intersections(&shape, position.0, rotation.as_radians(), SpatialQueryFilter::default())
+        .into_iter()
+        .any(|entity| entity != player && scalable_query.contains(entity));
+
+    if squished {
+        squished_evw.send(Squished);
+
+        if settings.get_single().map(|settings| settings.crush).unwrap_or_default() == CrushBehaviour::Respawn {
+            run.deaths += 1;
+            respawn_evw.send(Respawn);
+        }
+    }
+}
 
-pub fn respawn(mut q: Query<(&mut Transform, &mut LinearVelocity), With<Player>>, mut evr: EventReader<Respawn>) {
+pub fn respawn(
+    mut q: Query<(&mut Transform, &mut LinearVelocity), With<Player>>,
+    spawn_points: Query<&SpawnPoint>,
+    checkpoint: Res<ActiveCheckpoint>,
+    mut evr: EventReader<Respawn>,
+) {
     let Ok(mut player) = q.get_single_mut() else { return };
 
     if evr.read().last().is_some() {
-        player.0.translation.x = crate::camera::WINDOW_BOTTOM_LEFT.x + 150.;
-        player.0.translation.y = -100.0;
+        let position = match &checkpoint.0 {
+            Some(snapshot) => snapshot.position,
+            None => spawn_points.iter().next().map_or(DEFAULT_SPAWN, |spawn_point| spawn_point.0),
+        };
+        player.0.translation.x = position.x;
+        player.0.translation.y = position.y;
         player.1.0 = Vec2::ZERO;
     }
 }
@@ -38,9 +90,11 @@ impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<Respawn>()
+            .add_event::<Squished>()
             .add_systems(Update, (
                 check_out_of_bounds.in_set(GameplaySet::Update),
                 respawn.in_set(GameplaySet::Movement)
-            ));
+            ))
+            .add_systems(PostUpdate, check_squished.after(PhysicsSet::Sync).run_if(in_state(GameState::InGame).and_then(in_state(PauseState::Running))));
     }
 }
@@ -0,0 +1,313 @@
+//! Saves level progress and settings between runs
+use std::{fs, io, path::{Path, PathBuf}};
+
+use bevy::{prelude::*, window::{PrimaryWindow, WindowMode}};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{input::InputBindings, level::LevelProgress};
+
+/// Upgrade steps between save versions, where entry `n` takes a version `n + 1` save to version `n + 2`.
+/// Add a step here whenever the saved data changes shape.
+const MIGRATIONS: &[fn(&mut Value)] = &[];
+/// The save version written by this build
+const SAVE_VERSION: usize = MIGRATIONS.len() + 1;
+const SAVE_FILE: &str = "save.json";
+
+// RESOURCES
+/// Settings chosen by the player in the menu
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct UserSettings {
+    pub fullscreen: bool,
+    /// How far a gamepad stick or trigger must move before it counts, from 0 to 1
+    pub deadzone: f32,
+    /// Multiplies the rate of analog scaling
+    pub scale_sensitivity: f32,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            deadzone: 0.2,
+            scale_sensitivity: 1.,
+        }
+    }
+}
+
+/// Where the save file lives, if anywhere
+#[derive(Resource)]
+struct SavePath(Option<PathBuf>);
+
+/// Everything written to the save file
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SaveData {
+    progress: LevelProgress,
+    settings: UserSettings,
+    bindings: InputBindings,
+}
+
+// SAVE FILE
+/// Why a save file couldn't be read
+#[derive(Debug)]
+enum SaveError {
+    /// Written by a newer build, so this one can't understand it but mustn't throw it away either
+    TooNew(usize),
+    Corrupt(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::TooNew(version) => write!(f, "written by a newer version (save version {version})"),
+            SaveError::Corrupt(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// The platform's per-user data directory, or `None` where there is no filesystem
+fn data_dir() -> Option<PathBuf> {
+    if cfg!(target_arch = "wasm32") {
+        return None;
+    }
+
+    let env = |key: &str| std::env::var_os(key).filter(|value| !value.is_empty()).map(PathBuf::from);
+    let base = if cfg!(target_os = "windows") {
+        env("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        env("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        env("XDG_DATA_HOME").or_else(|| env("HOME").map(|home| home.join(".local/share")))
+    };
+
+    base.map(|dir| dir.join("entangled"))
+}
+
+/// Brings a save from any older version up to `SAVE_VERSION`
+fn migrate(value: Value) -> Result<Value, SaveError> {
+    apply_migrations(value, MIGRATIONS)
+}
+
+fn apply_migrations(mut value: Value, migrations: &[fn(&mut Value)]) -> Result<Value, SaveError> {
+    let latest = migrations.len() + 1;
+    let version = value.get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| SaveError::Corrupt(String::from("missing version")))? as usize;
+    if version > latest {
+        return Err(SaveError::TooNew(version));
+    }
+    if version == 0 {
+        return Err(SaveError::Corrupt(String::from("unsupported version 0")));
+    }
+
+    for step in &migrations[version - 1..] {
+        step(&mut value);
+    }
+    value["version"] = latest.into();
+
+    Ok(value)
+}
+
+fn parse(text: &str) -> Result<SaveData, SaveError> {
+    let value = serde_json::from_str(text).map_err(|err| SaveError::Corrupt(err.to_string()))?;
+    serde_json::from_value(migrate(value)?).map_err(|err| SaveError::Corrupt(err.to_string()))
+}
+
+/// Reads the save file, moving it aside and starting fresh if it cannot be understood.
+/// Returns `None` for a save from a newer version, which is left where it is and must not be written over.
+fn load(path: &Path) -> Option<SaveData> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Some(SaveData::default()),
+        Err(err) => {
+            warn!("Could not read save file {}: {err}", path.display());
+            return Some(SaveData::default());
+        },
+    };
+
+    match parse(&text) {
+        Ok(data) => Some(data),
+        Err(err @ SaveError::TooNew(_)) => {
+            warn!("Save file {} was {err}, so progress won't be saved until the game is updated", path.display());
+            None
+        },
+        Err(err) => {
+            let backup = path.with_extension("json.corrupt");
+            warn!("Save file {} is corrupt ({err}), moving it to {}", path.display(), backup.display());
+            if let Err(err) = fs::rename(path, &backup) {
+                warn!("Could not back up corrupt save file: {err}");
+            }
+            Some(SaveData::default())
+        },
+    }
+}
+
+/// Writes to a temporary file first so a crash mid-write cannot corrupt the save
+fn write(path: &Path, data: &SaveData) -> io::Result<()> {
+    let mut value = serde_json::to_value(data)?;
+    value["version"] = SAVE_VERSION.into();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&value)?)?;
+    fs::rename(&tmp, path)
+}
+
+// SYSTEMS
+fn save_game(path: Res<SavePath>, progress: Res<LevelProgress>, settings: Res<UserSettings>, bindings: Res<InputBindings>) {
+    let Some(path) = &path.0 else { return };
+
+    let data = SaveData {
+        progress: progress.clone(),
+        settings: settings.clone(),
+        bindings: bindings.clone(),
+    };
+    if let Err(err) = write(path, &data) {
+        error!("Could not write save file {}: {err}", path.display());
+    }
+}
+
+fn apply_user_settings(settings: Res<UserSettings>, mut window_query: Query<&mut Window, With<PrimaryWindow>>) {
+    let Ok(mut window) = window_query.get_single_mut() else { return };
+
+    window.mode = if settings.fullscreen {
+        WindowMode::BorderlessFullscreen
+    } else {
+        WindowMode::Windowed
+    };
+}
+
+// PLUGIN
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        let path = data_dir().map(|dir| dir.join(SAVE_FILE));
+        // A save this build can't read is played without, rather than written over
+        let (data, path) = match path.as_deref().map(load) {
+            Some(Some(data)) => (data, path),
+            _ => (SaveData::default(), None),
+        };
+
+        app
+            .insert_resource(data.progress)
+            .insert_resource(data.settings)
+            .insert_resource(data.bindings)
+            .insert_resource(SavePath(path))
+            .add_systems(Update, apply_user_settings.run_if(resource_changed::<UserSettings>()))
+            .add_systems(Update, save_game.run_if(
+                resource_changed::<LevelProgress>()
+                    .or_else(resource_changed::<UserSettings>())
+                    .or_else(resource_changed::<InputBindings>())
+            ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// A fresh directory to keep save files in, removed by the caller
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("entangled-save-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn migrations_run_from_the_saved_version() {
+        let migrations: &[fn(&mut Value)] = &[
+            |value| value["first"] = true.into(),
+            |value| value["second"] = true.into(),
+        ];
+
+        let from_v1 = apply_migrations(json!({ "version": 1 }), migrations).unwrap();
+        assert_eq!(from_v1, json!({ "version": 3, "first": true, "second": true }));
+
+        let from_v2 = apply_migrations(json!({ "version": 2 }), migrations).unwrap();
+        assert_eq!(from_v2, json!({ "version": 3, "second": true }));
+
+        let current = apply_migrations(json!({ "version": 3 }), migrations).unwrap();
+        assert_eq!(current, json!({ "version": 3 }));
+    }
+
+    #[test]
+    fn unknown_versions_are_rejected() {
+        assert!(migrate(json!({})).is_err());
+        assert!(migrate(json!({ "version": "1" })).is_err());
+        assert!(matches!(migrate(json!({ "version": 0 })), Err(SaveError::Corrupt(_))));
+        assert!(matches!(migrate(json!({ "version": SAVE_VERSION + 1 })), Err(SaveError::TooNew(version)) if version == SAVE_VERSION + 1));
+    }
+
+    #[test]
+    fn parse_fills_in_missing_fields() {
+        let data = parse(&json!({ "version": SAVE_VERSION, "settings": { "deadzone": 0.5 } }).to_string()).unwrap();
+        assert_eq!(data.settings.deadzone, 0.5);
+        assert_eq!(data.settings.scale_sensitivity, UserSettings::default().scale_sensitivity);
+
+        assert!(parse("not json").is_err());
+        assert!(parse(&json!({ "settings": {} }).to_string()).is_err());
+    }
+
+    #[test]
+    fn written_saves_load_back() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join(SAVE_FILE);
+
+        let mut data = SaveData::default();
+        data.settings.fullscreen = true;
+        data.settings.deadzone = 0.35;
+        write(&path, &data).unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert!(loaded.settings.fullscreen);
+        assert_eq!(loaded.settings.deadzone, 0.35);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn corrupt_saves_are_moved_aside() {
+        let dir = temp_dir("corrupt");
+        let path = dir.join(SAVE_FILE);
+        fs::write(&path, "{ \"version\": 1, \"settings\": ").unwrap();
+
+        let loaded = load(&path).unwrap();
+        assert_eq!(loaded.settings.deadzone, UserSettings::default().deadzone);
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(path.with_extension("json.corrupt")).unwrap(), "{ \"version\": 1, \"settings\": ");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn newer_saves_are_left_alone() {
+        let dir = temp_dir("newer");
+        let path = dir.join(SAVE_FILE);
+        let text = json!({ "version": SAVE_VERSION + 1, "settings": { "deadzone": 0.5 } }).to_string();
+        fs::write(&path, &text).unwrap();
+
+        assert!(load(&path).is_none());
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        assert!(!path.with_extension("json.corrupt").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_saves_load_as_defaults() {
+        let dir = temp_dir("missing");
+        let loaded = load(&dir.join(SAVE_FILE)).unwrap();
+        assert_eq!(loaded.settings.deadzone, UserSettings::default().deadzone);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}